@@ -1,22 +1,52 @@
+use std::cmp::Reverse;
 use std::io::BufRead;
 
-pub fn aoc_1() -> Result<(u64, u64), std::io::Error> {
+use color_eyre::eyre::{eyre, Result};
+
+use crate::top_k::TopK;
+
+const DEFAULT_TOP: usize = 3;
+
+/// Usage: `1 [K]`. Part 2 is the total of the top K elves (3 by default);
+/// with K given, the top elves are also listed.
+pub fn aoc_1(args: &[String]) -> Result<(u64, u64)> {
+    let k = match args.first() {
+        Some(arg) => arg.parse()?,
+        None => DEFAULT_TOP,
+    };
+
+    // Ties go to the elf that comes first.
+    let mut top: TopK<(u64, Reverse<usize>)> = TopK::new(k.max(1));
+    let mut n_elves = 0;
+    let mut cur = None;
     let stdin = std::io::stdin();
-    let mut best: [u64; 4] = [0; 4];
-    let mut max = 0;
-    let mut cur = 0;
-    for line in stdin.lock().lines() {
-        if let Ok(num) = line?.parse::<u64>() {
-            cur += num;
-        } else {
-            max = std::cmp::max(max, cur);
-            best[0] = cur;
-            best.sort();
-            cur = 0;
+    for (i, line) in stdin.lock().lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            if let Some(total) = cur.take() {
+                n_elves += 1;
+                top.push((total, Reverse(n_elves)));
+            }
+            continue;
+        }
+        let num: u64 = line
+            .trim()
+            .parse()
+            .map_err(|err| eyre!("line {}: {}: {:?}", i + 1, err, line))?;
+        cur = Some(cur.unwrap_or(0) + num);
+    }
+    if let Some(total) = cur {
+        n_elves += 1;
+        top.push((total, Reverse(n_elves)));
+    }
+
+    let top = top.into_sorted_vec();
+    if !args.is_empty() {
+        for (total, Reverse(elf)) in top.iter().take(k) {
+            println!("elf {}: {}", elf, total);
         }
     }
-    max = std::cmp::max(max, cur);
-    best[0] = cur;
-    best.sort();
-    Ok((max, best[1] + best[2] + best[3]))
+    let max = top.first().map_or(0, |(total, _)| *total);
+    let sum = top.iter().take(k).map(|(total, _)| total).sum();
+    Ok((max, sum))
 }
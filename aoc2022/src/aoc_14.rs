@@ -1,16 +1,28 @@
 use std::cmp::max;
-use std::io::{stdin, Read};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{stdin, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
 
 use itertools::Itertools;
 use nom::bytes::complete::tag;
-use nom::character::complete::u64;
+use nom::character::complete::i64;
 use nom::combinator::{all_consuming, map_res};
 use nom::multi::{many0, separated_list0};
 use nom::sequence::{separated_pair, terminated};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
 
-type Line = (usize, usize, usize, usize);
+type Line = (isize, isize, isize, isize);
+type Pos = (isize, isize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cell {
+    Rock,
+    Sand,
+}
 
 #[derive(PartialEq, Eq)]
 enum Outcome {
@@ -19,16 +31,16 @@ enum Outcome {
     Blocked,
 }
 
-const START_X: usize = 500;
-const START_Y: usize = 0;
+const START_X: isize = 500;
+const START_Y: isize = 0;
 
+/// Sparse sand simulation: only occupied cells are stored, so walls and sand
+/// can be anywhere. The optional floor is infinitely wide.
 #[derive(Debug)]
 struct Sandbox {
-    x0: usize,
-    y0: usize,
-    w: usize,
-    h: usize,
-    grid: Vec<bool>,
+    ymax: isize,
+    floor: Option<isize>,
+    grid: HashMap<Pos, Cell>,
 }
 
 fn delta<T>(a: T, b: T) -> isize
@@ -45,82 +57,89 @@ where
 }
 
 impl Sandbox {
-    fn new(x0: usize, y0: usize, w: usize, h: usize) -> Self {
-        let mut grid = Vec::new();
-        grid.resize(w * h, false);
-        Sandbox { x0, y0, w, h, grid }
+    fn new(ymax: isize, floor: Option<isize>) -> Self {
+        Sandbox {
+            ymax,
+            floor,
+            grid: HashMap::new(),
+        }
     }
 
-    fn in_bounds(&self, x: usize, y: usize) -> bool {
-        x >= self.x0 && x < self.x0 + self.w && y >= self.y0 && y < self.y0 + self.h
+    fn in_bounds(&self, y: isize) -> bool {
+        self.floor.is_some() || y <= self.ymax
     }
 
-    fn get(&self, x: usize, y: usize) -> bool {
-        if !self.in_bounds(x, y) {
-            return false;
+    fn get(&self, x: isize, y: isize) -> bool {
+        match self.floor {
+            Some(floor) if y >= floor => true,
+            _ => self.grid.contains_key(&(x, y)),
         }
-        self.grid[(y - self.y0) * self.w + x - self.x0]
     }
 
-    fn set(&mut self, x: usize, y: usize, val: bool) {
-        self.grid[(y - self.y0) * self.w + x - self.x0] = val
+    fn set(&mut self, x: isize, y: isize, cell: Cell) {
+        self.grid.insert((x, y), cell);
     }
 
-    fn draw_line(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+    fn draw_line(&mut self, x1: isize, y1: isize, x2: isize, y2: isize) {
         let dx = delta(x1, x2);
         let dy = delta(y1, y2);
         let mut x = x1;
         let mut y = y1;
         while x != x2 || y != y2 {
-            self.set(x, y, true);
-            x = ((x as isize) + dx) as usize;
-            y = ((y as isize) + dy) as usize;
+            self.set(x, y, Cell::Rock);
+            x += dx;
+            y += dy;
         }
-        self.set(x, y, true);
+        self.set(x, y, Cell::Rock);
     }
 
-    fn from_lines(lines: &Vec<Line>, bottom: bool) -> Self {
-        let x0 = 0;
-        let xmax = 1000;
-        let y0 = 0;
+    fn from_lines(lines: &[Line], bottom: bool) -> Self {
         let ymax = lines
-            .into_iter()
+            .iter()
             .map(|(_, y1, _, y2)| max(*y1, *y2))
             .max()
-            .unwrap();
-        let mut sandbox = Self::new(x0, y0, xmax - x0 + 1, ymax - y0 + 3);
+            .unwrap_or(START_Y);
+        let floor = if bottom { Some(ymax + 2) } else { None };
+        let mut sandbox = Self::new(ymax, floor);
         for (x1, y1, x2, y2) in lines {
             sandbox.draw_line(*x1, *y1, *x2, *y2);
         }
-        if bottom {
-            sandbox.draw_line(x0, ymax + 2, xmax, ymax + 2)
-        }
         sandbox
     }
 
-    fn try_grain(&mut self) -> Outcome {
-        let mut x = START_X;
-        let mut y = START_Y;
-        if self.get(x, y) {
-            return Outcome::Blocked;
+    fn next_pos(&self, x: isize, y: isize) -> Option<Pos> {
+        [(x, y + 1), (x - 1, y + 1), (x + 1, y + 1)]
+            .into_iter()
+            .find(|(x, y)| !self.get(*x, *y))
+    }
+
+    /// Positions a new grain passes through, and what happens to it at the
+    /// end. The sandbox is not modified.
+    fn fall_path(&self) -> (Vec<Pos>, Outcome) {
+        let mut pos = (START_X, START_Y);
+        if self.get(pos.0, pos.1) {
+            return (vec![], Outcome::Blocked);
         }
-        loop {
-            if !self.get(x, y + 1) {
-                y += 1;
-            } else if !self.get(x - 1, y + 1) {
-                x -= 1;
-                y += 1;
-            } else if !self.get(x + 1, y + 1) {
-                x += 1;
-                y += 1;
-            } else {
-                self.set(x, y, true);
-                return Outcome::Rest;
-            }
-            if !self.in_bounds(x, y) {
-                return Outcome::Fall;
+        let mut path = vec![pos];
+        while let Some(next) = self.next_pos(pos.0, pos.1) {
+            pos = next;
+            path.push(pos);
+            if !self.in_bounds(pos.1) {
+                return (path, Outcome::Fall);
             }
         }
+        (path, Outcome::Rest)
+    }
+
+    /// Drop a grain along its `fall_path`, leaving it there if it comes to
+    /// rest.
+    fn try_grain(&mut self) -> Outcome {
+        let (path, outcome) = self.fall_path();
+        if outcome == Outcome::Rest {
+            let (x, y) = path[path.len() - 1];
+            self.set(x, y, Cell::Sand);
+        }
+        outcome
     }
 
     fn count_grains_until(&mut self, outcome: Outcome) -> usize {
@@ -132,6 +151,193 @@ impl Sandbox {
             i += 1;
         }
     }
+
+    /// Count the cells that sand from the source can reach, row by row. With
+    /// a floor, this is the number of grains that come to rest before the
+    /// source is blocked. Without a floor the sand falls forever, so there is
+    /// no answer.
+    fn flood_from_source(&self) -> Option<usize> {
+        self.floor?;
+        let mut row: HashSet<isize> = HashSet::new();
+        if !self.get(START_X, START_Y) {
+            row.insert(START_X);
+        }
+        let mut y = START_Y;
+        let mut count = 0;
+        while !row.is_empty() {
+            count += row.len();
+            y += 1;
+            row = row
+                .iter()
+                .flat_map(|x| [x - 1, *x, x + 1])
+                .filter(|x| !self.get(*x, y))
+                .collect();
+        }
+        Some(count)
+    }
+
+    /// Area worth drawing: everything sand can reach, plus one cell of margin
+    /// (or the floor) at the bottom.
+    fn bounds(&self) -> (isize, isize, isize, isize) {
+        let (mut x0, mut x1) = self
+            .grid
+            .keys()
+            .map(|(x, _)| *x)
+            .chain([START_X])
+            .minmax()
+            .into_option()
+            .unwrap();
+        x0 -= 1;
+        x1 += 1;
+        if let Some(floor) = self.floor {
+            x0 = x0.min(START_X - (floor - START_Y));
+            x1 = x1.max(START_X + (floor - START_Y));
+        }
+        (x0, START_Y, x1, self.floor.unwrap_or(self.ymax + 1))
+    }
+
+    fn tile(&self, x: isize, y: isize, grain: Option<Pos>) -> Tile {
+        if grain == Some((x, y)) {
+            return Tile::Grain;
+        }
+        match (self.grid.get(&(x, y)), self.floor) {
+            (Some(Cell::Rock), _) => Tile::Rock,
+            (Some(Cell::Sand), _) => Tile::Sand,
+            (None, Some(floor)) if y >= floor => Tile::Rock,
+            (None, _) if (x, y) == (START_X, START_Y) => Tile::Source,
+            (None, _) => Tile::Air,
+        }
+    }
+
+    fn render(&self, bounds: (isize, isize, isize, isize), grain: Option<Pos>) -> String {
+        let (x0, y0, x1, y1) = bounds;
+        let mut s = String::new();
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                s.push(self.tile(x, y, grain).char());
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Drop grains until one does not come to rest, showing up to
+    /// `frames_per_grain` frames along each grain's path. Returns the number
+    /// of grains that came to rest.
+    fn animate(&mut self, frames_per_grain: usize, sink: &mut dyn FrameSink) -> Result<usize> {
+        let bounds = self.bounds();
+        let mut i = 0;
+        loop {
+            let (path, outcome) = self.fall_path();
+            let n = frames_per_grain.min(path.len());
+            for k in 1..=n {
+                sink.frame(self, bounds, Some(path[k * path.len() / n - 1]))?;
+            }
+            if outcome != Outcome::Rest {
+                sink.frame(self, bounds, None)?;
+                return Ok(i);
+            }
+            let (x, y) = path[path.len() - 1];
+            self.set(x, y, Cell::Sand);
+            i += 1;
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Tile {
+    Air,
+    Rock,
+    Sand,
+    Grain,
+    Source,
+}
+
+impl Tile {
+    fn char(self) -> char {
+        match self {
+            Tile::Air => '.',
+            Tile::Rock => '#',
+            Tile::Sand => 'o',
+            Tile::Grain => '~',
+            Tile::Source => '+',
+        }
+    }
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Tile::Air => [16, 16, 32],
+            Tile::Rock => [128, 128, 128],
+            Tile::Sand => [224, 192, 96],
+            Tile::Grain => [255, 64, 32],
+            Tile::Source => [96, 160, 255],
+        }
+    }
+}
+
+trait FrameSink {
+    fn frame(
+        &mut self,
+        sandbox: &Sandbox,
+        bounds: (isize, isize, isize, isize),
+        grain: Option<Pos>,
+    ) -> Result<()>;
+}
+
+/// Redraws the sandbox in place using ANSI cursor control.
+struct Terminal {
+    delay: Duration,
+}
+
+impl FrameSink for Terminal {
+    fn frame(
+        &mut self,
+        sandbox: &Sandbox,
+        bounds: (isize, isize, isize, isize),
+        grain: Option<Pos>,
+    ) -> Result<()> {
+        let mut out = std::io::stdout().lock();
+        write!(out, "\x1b[H{}", sandbox.render(bounds, grain))?;
+        out.flush()?;
+        sleep(self.delay);
+        Ok(())
+    }
+}
+
+/// Writes each frame as a numbered binary PPM image.
+struct PpmFrames {
+    dir: PathBuf,
+    scale: usize,
+    n_frames: usize,
+}
+
+impl FrameSink for PpmFrames {
+    fn frame(
+        &mut self,
+        sandbox: &Sandbox,
+        bounds: (isize, isize, isize, isize),
+        grain: Option<Pos>,
+    ) -> Result<()> {
+        let (x0, y0, x1, y1) = bounds;
+        let w = (x1 - x0 + 1) as usize * self.scale;
+        let h = (y1 - y0 + 1) as usize * self.scale;
+        let path = self.dir.join(format!("frame_{:06}.ppm", self.n_frames));
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", w, h)?;
+        for y in y0..=y1 {
+            let row: Vec<[u8; 3]> = (x0..=x1).map(|x| sandbox.tile(x, y, grain).rgb()).collect();
+            for _ in 0..self.scale {
+                for rgb in row.iter() {
+                    for _ in 0..self.scale {
+                        out.write_all(rgb)?;
+                    }
+                }
+            }
+        }
+        out.flush()?;
+        self.n_frames += 1;
+        Ok(())
+    }
 }
 
 fn parse_file(s: &str) -> nom::IResult<&str, Vec<Line>> {
@@ -143,7 +349,7 @@ fn parse_file(s: &str) -> nom::IResult<&str, Vec<Line>> {
 fn parse_lines(s: &str) -> nom::IResult<&str, Vec<Line>> {
     let (rest, pairs) = separated_list0(
         tag(" -> "),
-        separated_pair(parse_usize, tag(","), parse_usize),
+        separated_pair(parse_isize, tag(","), parse_isize),
     )(s)?;
 
     let lines: Vec<Line> = pairs
@@ -155,21 +361,60 @@ fn parse_lines(s: &str) -> nom::IResult<&str, Vec<Line>> {
     Ok((rest, lines))
 }
 
-fn parse_usize(s: &str) -> nom::IResult<&str, usize> {
-    map_res(u64, usize::try_from)(s)
+fn parse_isize(s: &str) -> nom::IResult<&str, isize> {
+    map_res(i64, isize::try_from)(s)
+}
+
+const FRAME_DELAY: Duration = Duration::from_millis(20);
+const PPM_SCALE: usize = 4;
+
+fn parse_animation_args(args: &[String]) -> Result<(usize, bool)> {
+    let frames_per_grain = match args.first() {
+        Some(arg) => arg.parse()?,
+        None => 1,
+    };
+    let bottom = match args.get(1).map(String::as_str) {
+        None => false,
+        Some("floor") => true,
+        Some(arg) => return Err(eyre!("expected 'floor', got: {}", arg)),
+    };
+    Ok((frames_per_grain, bottom))
 }
 
-pub fn aoc_14() -> Result<(usize, usize)> {
+/// Usage: `14 [animate [FRAMES_PER_GRAIN] [floor] | ppm DIR [FRAMES_PER_GRAIN] [floor]]`
+pub fn aoc_14(args: &[String]) -> Result<(usize, usize)> {
     let mut s = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
-    let (_rest, lines) = all_consuming(parse_file)(&s).or_else(|err| Err(err.to_owned()))?;
+    let (_rest, lines) = all_consuming(parse_file)(&s).map_err(|err| err.to_owned())?;
+
+    match args.first().map(String::as_str) {
+        None => (),
+        Some("animate") => {
+            let (frames_per_grain, bottom) = parse_animation_args(&args[1..])?;
+            let mut sandbox = Sandbox::from_lines(&lines, bottom);
+            print!("\x1b[2J");
+            sandbox.animate(frames_per_grain, &mut Terminal { delay: FRAME_DELAY })?;
+        }
+        Some("ppm") => {
+            let dir = args.get(1).ok_or(eyre!("missing output directory"))?;
+            let (frames_per_grain, bottom) = parse_animation_args(&args[2..])?;
+            let mut sandbox = Sandbox::from_lines(&lines, bottom);
+            let mut sink = PpmFrames {
+                dir: PathBuf::from(dir),
+                scale: PPM_SCALE,
+                n_frames: 0,
+            };
+            sandbox.animate(frames_per_grain, &mut sink)?;
+        }
+        Some(arg) => return Err(eyre!("unrecognized mode: {}", arg)),
+    }
 
     let mut sandbox1 = Sandbox::from_lines(&lines, false);
     let result1 = sandbox1.count_grains_until(Outcome::Fall);
 
-    let mut sandbox2 = Sandbox::from_lines(&lines, true);
-    let result2 = sandbox2.count_grains_until(Outcome::Blocked);
+    let sandbox2 = Sandbox::from_lines(&lines, true);
+    let result2 = sandbox2.flood_from_source().unwrap();
 
     Ok((result1, result2))
 }
@@ -1,7 +1,7 @@
-use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::io::{stdin, Read};
 
+use itertools::Itertools;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while_m_n};
 use nom::character::complete::u64;
@@ -9,7 +9,9 @@ use nom::combinator::{all_consuming, map, map_res};
 use nom::multi::separated_list1;
 use nom::sequence::tuple;
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
+
+use crate::graph::{DistanceMatrix, Graph};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct Name([char; 2]);
@@ -53,9 +55,9 @@ impl Valve {
 
 fn parse_name(s: &str) -> nom::IResult<&str, Name> {
     map(
-        take_while_m_n(2, 2, |c: char| 'A' <= c && c <= 'Z'),
+        take_while_m_n(2, 2, |c: char| c.is_ascii_uppercase()),
         |s: &str| {
-            let chars = [s.chars().nth(0).unwrap(), s.chars().nth(1).unwrap()];
+            let chars = [s.chars().next().unwrap(), s.chars().nth(1).unwrap()];
             Name(chars)
         },
     )(s)
@@ -67,181 +69,291 @@ fn parse_usize(s: &str) -> nom::IResult<&str, usize> {
 
 const START_NAME: Name = Name(['A', 'A']);
 const MINUTES: usize = 30;
+const TEACHING: usize = 4;
+/// The optimiser keeps tables over all subsets of the positive-flow valves,
+/// and splitting them between agents takes O(3^n) steps.
+const MAX_VALVES: usize = 24;
 
 #[derive(Debug)]
 struct DistanceMap {
-    names: Vec<Name>,
+    graph: Graph<Name>,
+    /// Positive-flow valves. Their positions are the bit indexes used by the
+    /// optimiser.
     nodes: Vec<(Name, usize)>,
-    distances: HashMap<(Name, Name), usize>,
+    /// Distances between the positive-flow valves.
+    distances: DistanceMatrix,
 }
 
-struct Backtrack<'a, const N: usize> {
-    map: &'a DistanceMap,
-    node_set: HashSet<(Name, usize)>,
-    best: usize,
+/// Parameters of a valve-opening run.
+#[derive(Clone, Copy, Debug)]
+struct Config {
+    agents: usize,
+    minutes: usize,
+    start: Name,
+    /// Minutes spent before any agent starts moving.
+    teaching: usize,
 }
 
-struct BacktrackState<const N: usize> {
-    current: [Name; N],
-    next_move: [usize; N],
-    minutes_left: usize,
-    total: usize,
-    unopened: usize,
+#[derive(Clone, Copy, Debug)]
+struct Opening {
+    valve: Name,
+    /// Minute (counting from the very start) after which the valve is open.
+    minute: usize,
 }
 
-impl<'a, const N: usize> Backtrack<'a, N> {
-    fn new(map: &'a DistanceMap) -> Self {
-        Self {
-            map,
-            node_set: HashSet::from_iter(map.nodes.clone().into_iter()),
-            best: 0,
-        }
-    }
+#[derive(Debug)]
+struct Plan {
+    total: usize,
+    schedules: Vec<Vec<Opening>>,
+}
 
-    fn backtrack(&mut self, minutes: usize) -> usize {
-        let state = BacktrackState {
-            current: [START_NAME; N],
-            next_move: [minutes; N],
-            minutes_left: minutes,
-            total: 0,
-            unopened: self.map.nodes.iter().map(|(_, flow)| flow).sum(),
-        };
-        self.move_step(state, 0);
-        self.best
-    }
+/// Best single-agent route for every subset of the positive-flow valves: the
+/// total released, and the route as (valve index, minutes left) pairs.
+struct SubsetTable {
+    best: Vec<usize>,
+    routes: Vec<Vec<(usize, usize)>>,
+}
 
-    fn get_distance(&self, a: Name, b: Name) -> Option<usize> {
-        self.map.distances.get(&(a, b)).copied()
-    }
+struct Search<'a> {
+    flows: Vec<usize>,
+    from_start: Vec<Option<usize>>,
+    distances: &'a DistanceMatrix,
+    table: &'a mut SubsetTable,
+}
 
-    fn move_step(&mut self, state: BacktrackState<N>, i: usize) {
-        if i == N {
-            return self.wait_step(state);
+impl<'a> Search<'a> {
+    fn visit(
+        &mut self,
+        at: Option<usize>,
+        left: usize,
+        mask: usize,
+        total: usize,
+        route: &mut Vec<(usize, usize)>,
+    ) {
+        if total > self.table.best[mask] {
+            self.table.best[mask] = total;
+            self.table.routes[mask] = route.clone();
         }
-        if state.next_move[i] < state.minutes_left {
-            return self.move_step(state, i + 1);
-        }
-        let prev = state.current[i];
-        let nodes: Vec<(Name, usize)> = self.node_set.iter().cloned().collect();
-        for node @ (name, flow) in nodes.iter() {
-            self.node_set.remove(node);
-
-            if let Some(dist) = self.get_distance(prev, *name) {
-                if dist + 1 < state.minutes_left {
-                    let mut current = state.current.clone();
-                    current[i] = *name;
-                    let mut next_move = state.next_move.clone();
-                    next_move[i] = state.minutes_left - dist - 1;
-                    let state = BacktrackState {
-                        current,
-                        next_move,
-                        total: state.total + flow * (state.minutes_left - dist - 1),
-                        unopened: state.unopened - flow,
-                        ..state
-                    };
-                    self.move_step(state, i + 1);
-                }
+        for j in 0..self.flows.len() {
+            if mask & (1 << j) != 0 {
+                continue;
+            }
+            let dist = match at {
+                Some(i) => self.distances.get(i, j),
+                None => self.from_start[j],
+            };
+            let Some(dist) = dist else {
+                continue;
+            };
+            if dist + 1 < left {
+                let left = left - dist - 1;
+                route.push((j, left));
+                self.visit(
+                    Some(j),
+                    left,
+                    mask | (1 << j),
+                    total + self.flows[j] * left,
+                    route,
+                );
+                route.pop();
             }
-
-            self.node_set.insert(*node);
         }
     }
+}
 
-    fn wait_step(&mut self, state: BacktrackState<N>) {
-        self.best = self.best.max(state.total);
-
-        if self.node_set.is_empty() {
-            return;
+impl DistanceMap {
+    fn new(valves: &[Valve]) -> Self {
+        let mut graph = Graph::new();
+        for valve in valves.iter() {
+            graph.intern(valve.name);
+            for tunnel in valve.tunnels.iter() {
+                graph.add_edge(valve.name, *tunnel);
+            }
         }
-        let minutes_left = *state.next_move.iter().max().unwrap();
-        assert!(minutes_left < state.minutes_left);
-
-        let estimated = state.unopened * (minutes_left - 1);
-        if state.total + estimated <= self.best {
-            return;
+        let nodes: Vec<(Name, usize)> = valves
+            .iter()
+            .filter(|valve| valve.flow > 0)
+            .map(|valve| (valve.name, valve.flow))
+            .collect();
+        let ids: Vec<u16> = nodes.iter().map(|(name, _)| graph.intern(*name)).collect();
+        let distances = graph.distance_matrix(&ids);
+        Self {
+            graph,
+            nodes,
+            distances,
         }
-
-        self.move_step(
-            BacktrackState {
-                minutes_left,
-                ..state
-            },
-            0,
-        );
     }
-}
 
-impl DistanceMap {
-    fn new() -> Self {
-        Self {
-            names: vec![],
-            nodes: vec![],
-            distances: HashMap::new(),
+    #[allow(dead_code)]
+    fn dump(&self) {
+        for (i, (a, _)) in self.nodes.iter().enumerate() {
+            for (j, (b, _)) in self.nodes.iter().enumerate() {
+                println!("{:?} -> {:?}: {:?}", a, b, self.distances.get(i, j));
+            }
         }
     }
 
-    fn add_valve(&mut self, valve: &Valve) {
-        self.names.push(valve.name);
-        if valve.flow > 0 {
-            self.nodes.push((valve.name, valve.flow));
+    fn subset_table(&self, start: Name, budget: usize) -> Result<SubsetTable> {
+        let n = self.nodes.len();
+        if n > MAX_VALVES {
+            return Err(eyre!("too many valves with positive flow: {}", n));
         }
-        self.distances.insert((valve.name, valve.name), 0);
-        for tunnel in valve.tunnels.iter() {
-            self.distances.insert((valve.name, *tunnel), 1);
+        let mut table = SubsetTable {
+            best: vec![0; 1 << n],
+            routes: vec![vec![]; 1 << n],
+        };
+        let start = self
+            .graph
+            .id(start)
+            .ok_or(eyre!("unknown valve: {:?}", start))?;
+        let from_start = self.graph.bfs(start);
+        let mut search = Search {
+            flows: self.nodes.iter().map(|(_, flow)| *flow).collect(),
+            from_start: self
+                .nodes
+                .iter()
+                .map(|(name, _)| from_start[self.graph.id(*name).unwrap() as usize])
+                .collect(),
+            distances: &self.distances,
+            table: &mut table,
+        };
+        search.visit(None, budget, 0, 0, &mut vec![]);
+
+        // Make every entry the best over all its subsets, so that agents can
+        // be given disjoint valve sets that don't have to be used completely.
+        for bit in 0..n {
+            for mask in 0..1 << n {
+                if mask & (1 << bit) != 0 && table.best[mask ^ (1 << bit)] > table.best[mask] {
+                    table.best[mask] = table.best[mask ^ (1 << bit)];
+                    table.routes[mask] = table.routes[mask ^ (1 << bit)].clone();
+                }
+            }
         }
+        Ok(table)
     }
 
-    fn floyd_warshall(&mut self) {
-        for c in self.names.iter() {
-            for a in self.names.iter() {
-                for b in self.names.iter() {
-                    let ab = self.distances.get(&(*a, *b)).unwrap_or(&(usize::MAX / 2));
-                    let ac = self.distances.get(&(*a, *c)).unwrap_or(&(usize::MAX / 2));
-                    let cb = self.distances.get(&(*c, *b)).unwrap_or(&(usize::MAX / 2));
-                    if *ab > *ac + *cb {
-                        self.distances.insert((*a, *b), *ac + *cb);
+    /// Split the positive-flow valves between the agents, using DP over
+    /// bitmasks: `combined[k][mask]` is the best total for `k + 1` agents
+    /// restricted to the valves in `mask`.
+    fn find_best(&self, config: &Config) -> Result<Plan> {
+        let budget = config.minutes.saturating_sub(config.teaching);
+        let table = self.subset_table(config.start, budget)?;
+        let full = (1 << self.nodes.len()) - 1;
+
+        let mut combined = vec![table.best.clone()];
+        let mut choices: Vec<Vec<usize>> = vec![(0..=full).collect()];
+        for _ in 1..config.agents {
+            let prev = combined.last().unwrap();
+            let mut best = vec![0; full + 1];
+            let mut choice = vec![0; full + 1];
+            for mask in 0..=full {
+                // Iterate over all subsets of mask.
+                let mut sub = mask;
+                loop {
+                    let total = table.best[sub] + prev[mask ^ sub];
+                    if total > best[mask] {
+                        best[mask] = total;
+                        choice[mask] = sub;
+                    }
+                    if sub == 0 {
+                        break;
                     }
+                    sub = (sub - 1) & mask;
                 }
             }
+            combined.push(best);
+            choices.push(choice);
         }
-    }
 
-    #[allow(dead_code)]
-    fn dump(&self) {
-        for (a, _) in self.nodes.iter() {
-            for (b, _) in self.nodes.iter() {
-                println!("{:?} -> {:?}: {:?}", a, b, self.distances.get(&(*a, *b)));
-            }
+        let mut schedules = vec![];
+        let mut mask = full;
+        for choice in choices.iter().rev().take(config.agents) {
+            let sub = choice[mask];
+            let schedule = table.routes[sub]
+                .iter()
+                .map(|(i, left)| Opening {
+                    valve: self.nodes[*i].0,
+                    minute: config.minutes - left,
+                })
+                .collect();
+            schedules.push(schedule);
+            mask ^= sub;
         }
-    }
 
-    fn find_best(&self) -> usize {
-        let mut state = Backtrack::<1>::new(self);
-        state.backtrack(MINUTES)
+        Ok(Plan {
+            total: combined.last().map_or(0, |best| best[full]),
+            schedules,
+        })
     }
+}
 
-    fn find_best_2(&self) -> usize {
-        let mut state = Backtrack::<2>::new(self);
-        state.backtrack(MINUTES - 4)
+fn parse_config(args: &[String]) -> Result<Config> {
+    let [agents, minutes, teaching, rest @ ..] = args else {
+        return Err(eyre!("expected: AGENTS MINUTES TEACHING [START]"));
+    };
+    let start = match rest {
+        [] => START_NAME,
+        [start] => {
+            all_consuming(parse_name)(start)
+                .map_err(|err| err.to_owned())?
+                .1
+        }
+        _ => return Err(eyre!("too many arguments")),
+    };
+    let agents = agents.parse()?;
+    if agents == 0 {
+        return Err(eyre!("need at least one agent"));
     }
+    Ok(Config {
+        agents,
+        minutes: minutes.parse()?,
+        start,
+        teaching: teaching.parse()?,
+    })
 }
 
-pub fn aoc_16() -> Result<(usize, usize)> {
+/// Usage: `16 [AGENTS MINUTES TEACHING [START]]`; with arguments, also print
+/// the plan for that configuration.
+pub fn aoc_16(args: &[String]) -> Result<(usize, usize)> {
     let mut s = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
-    let mut map = DistanceMap::new();
-
+    let mut valves = vec![];
     for line in s.lines() {
-        let (_rest, valve) =
-            all_consuming(Valve::parse)(line).or_else(|err| Err(err.to_owned()))?;
-
-        map.add_valve(&valve);
+        let (_rest, valve) = all_consuming(Valve::parse)(line).map_err(|err| err.to_owned())?;
+        valves.push(valve);
     }
-    map.floyd_warshall();
+
+    let map = DistanceMap::new(&valves);
     //map.dump();
-    let result = map.find_best();
-    let result2 = map.find_best_2();
+    if !args.is_empty() {
+        let plan = map.find_best(&parse_config(args)?)?;
+        println!("total: {}", plan.total);
+        for (i, schedule) in plan.schedules.iter().enumerate() {
+            let openings = schedule
+                .iter()
+                .map(|opening| format!("{:?} at {}", opening.valve, opening.minute))
+                .join(", ");
+            println!("agent {}: {}", i + 1, openings);
+        }
+    }
+
+    let result = map
+        .find_best(&Config {
+            agents: 1,
+            minutes: MINUTES,
+            start: START_NAME,
+            teaching: 0,
+        })?
+        .total;
+    let result2 = map
+        .find_best(&Config {
+            agents: 2,
+            minutes: MINUTES,
+            start: START_NAME,
+            teaching: TEACHING,
+        })?
+        .total;
 
     Ok((result, result2))
 }
@@ -3,12 +3,37 @@ use std::{
     io::{stdin, Read},
 };
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
 use itertools::Itertools;
 
-#[derive(Clone)]
+const DEFAULT_ROCKS: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+const DEFAULT_WIDTH: usize = 7;
+const TARGETS: [usize; 2] = [2022, 1000000000000];
+
+/// A rock (or a slice of the chamber), one bit per cell, bottom row first.
+/// Column `x` of a chamber with width `w` is bit `w - 1 - x`.
+#[derive(Clone, Debug)]
 struct Rock {
-    lines: [u8; 4],
+    lines: Vec<u64>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -18,97 +43,141 @@ enum Shift {
 }
 
 impl Rock {
-    fn shift(&mut self, shift: Shift, lines: &[u8; 4]) -> bool {
-        match shift {
-            Shift::Left => {
-                for i in 0..4 {
-                    if self.lines[i] & 0b0100_0000 != 0 || (self.lines[i] << 1) & lines[i] != 0 {
-                        return false;
-                    }
-                }
-                for x in self.lines.iter_mut() {
-                    *x <<= 1;
-                }
-                return true;
+    /// Parse rocks separated by blank lines, placed for a chamber of the
+    /// given width: left edge two units away from the left wall.
+    fn parse_all(s: &str, width: usize) -> Result<Vec<Self>> {
+        if width == 0 || width > 64 {
+            return Err(eyre!("unsupported chamber width: {}", width));
+        }
+        let mut rocks = vec![];
+        for block in s.split("\n\n").filter(|block| !block.trim().is_empty()) {
+            let rows = block.lines().collect_vec();
+            let rock_width = rows.iter().map(|row| row.len()).max().unwrap();
+            if rock_width + 2 > width {
+                return Err(eyre!("rock too wide for chamber: {:?}", block));
             }
-            Shift::Right => {
-                for i in 0..4 {
-                    if self.lines[i] & 0b1 != 0 || (self.lines[i] >> 1) & lines[i] != 0 {
-                        return false;
+            let mut lines = vec![];
+            for row in rows.iter().rev() {
+                let mut line = 0;
+                for (x, c) in row.chars().enumerate() {
+                    match c {
+                        '#' => line |= 1 << (width - 3 - x),
+                        '.' => (),
+                        _ => return Err(eyre!("unexpected character: {:?}", c)),
                     }
                 }
-                for x in self.lines.iter_mut() {
-                    *x >>= 1;
-                }
-                return true;
+                lines.push(line);
             }
+            rocks.push(Rock { lines });
         }
+        if rocks.is_empty() {
+            return Err(eyre!("no rocks"));
+        }
+        Ok(rocks)
     }
 
-    fn intersect(&self, lines: &[u8; 4]) -> bool {
-        (0..4).any(|i| self.lines[i] & lines[i] != 0)
+    fn height(&self) -> usize {
+        self.lines.len()
     }
 
-    fn apply(&self, lines: &mut [u8; 4]) {
-        for i in 0..4 {
-            assert!(self.lines[i] & lines[i] == 0);
-            lines[i] |= self.lines[i];
+    fn shift(&mut self, shift: Shift, width: usize, lines: &[u64]) -> bool {
+        let blocked = match shift {
+            Shift::Left => self
+                .lines
+                .iter()
+                .zip(lines)
+                .any(|(rock, line)| rock & (1 << (width - 1)) != 0 || (rock << 1) & line != 0),
+            Shift::Right => self
+                .lines
+                .iter()
+                .zip(lines)
+                .any(|(rock, line)| rock & 1 != 0 || (rock >> 1) & line != 0),
+        };
+        if blocked {
+            return false;
+        }
+        for x in self.lines.iter_mut() {
+            match shift {
+                Shift::Left => *x <<= 1,
+                Shift::Right => *x >>= 1,
+            }
+        }
+        true
+    }
+
+    fn intersect(&self, lines: &[u64]) -> bool {
+        self.lines
+            .iter()
+            .zip(lines)
+            .any(|(rock, line)| rock & line != 0)
+    }
+
+    fn apply(&self, lines: &mut [u64]) {
+        for (rock, line) in self.lines.iter().zip(lines) {
+            assert!(rock & *line == 0);
+            *line |= rock;
         }
     }
 }
 
-const ROCKS: [Rock; 5] = [
-    Rock {
-        lines: [0b0001_1110, 0, 0, 0],
-    },
-    Rock {
-        lines: [0b0000_1000, 0b0001_1100, 0b0000_1000, 0],
-    },
-    Rock {
-        lines: [0b0001_1100, 0b0000_0100, 0b0000_0100, 0],
-    },
-    Rock {
-        lines: [0b0001_0000, 0b0001_0000, 0b0001_0000, 0b0001_0000],
-    },
-    Rock {
-        lines: [0b0001_1000, 0b0001_1000, 0, 0],
-    },
-];
-
-const SNAPSHOT_SIZE: usize = 32;
-
-#[derive(PartialEq, Eq, Hash)]
+const SURFACE_LIMIT: usize = 256;
+
+/// Everything that determines how the well evolves from the moment a new
+/// rock appears: which jet and rock come next, and the empty cells a rock
+/// could still reach from above (top row first).
+#[derive(Clone, PartialEq, Eq, Hash)]
 struct Snapshot {
-    n_turns_mod: usize,
-    n_rocks_mod: usize,
-    last_rows: [u8; SNAPSHOT_SIZE],
+    jet: usize,
+    rock: usize,
+    surface: Vec<u64>,
 }
 
 #[derive(Copy, Clone, Debug)]
 struct Period {
     n_rocks: usize,
     n_rocks_delta: usize,
+    n_turns_delta: usize,
     top_delta: usize,
+    surface_depth: usize,
+}
+
+impl Period {
+    fn report(&self) -> String {
+        format!(
+            "period: {} rocks ({} jets) adding {} rows, starting after {} rocks; \
+             reachable surface {} rows deep",
+            self.n_rocks_delta,
+            self.n_turns_delta,
+            self.top_delta,
+            self.n_rocks,
+            self.surface_depth
+        )
+    }
 }
 
 struct Well {
-    lines: Vec<u8>,
+    width: usize,
+    lines: Vec<u64>,
     shifts: Vec<Shift>,
+    rocks: Vec<Rock>,
     rock: Rock,
     rock_pos: usize,
     n_turns: usize,
     n_rocks: usize,
 
-    snapshots: HashMap<Snapshot, (usize, usize)>,
+    /// For each snapshot seen: number of rocks, turns and height at that time.
+    snapshots: HashMap<Snapshot, (usize, usize, usize)>,
     period: Option<Period>,
 }
 
 impl Well {
-    fn new(shifts: Vec<Shift>) -> Self {
+    fn new(width: usize, shifts: Vec<Shift>, rocks: Vec<Rock>) -> Self {
         Self {
+            width,
             lines: vec![0],
             shifts,
-            rock: ROCKS[0].clone(),
+            rock: rocks[0].clone(),
+            rocks,
             rock_pos: 3,
             n_turns: 0,
             n_rocks: 0,
@@ -117,64 +186,86 @@ impl Well {
         }
     }
 
-    fn lines4(lines: &mut Vec<u8>, i: usize) -> &mut [u8; 4] {
-        if lines.len() < i + 4 {
-            lines.resize(i + 4, 0);
+    fn window(lines: &mut Vec<u64>, i: usize, n: usize) -> &mut [u64] {
+        if lines.len() < i + n {
+            lines.resize(i + n, 0);
         }
-        let slice = &mut lines[i..i + 4];
-        slice.try_into().unwrap()
+        &mut lines[i..i + n]
     }
 
     fn tick(&mut self) {
         let shift = self.shifts[self.n_turns % self.shifts.len()];
-        let lines = Self::lines4(&mut self.lines, self.rock_pos);
-        let _success = self.rock.shift(shift, lines);
-        // println!(
-        //     "shift {:?}{}",
-        //     shift,
-        //     if success { "" } else { " (nothing happens)" }
-        // );
+        let lines = Self::window(&mut self.lines, self.rock_pos, self.rock.height());
+        self.rock.shift(shift, self.width, lines);
 
         if self.rock_pos > 0
-            && !self
-                .rock
-                .intersect(Self::lines4(&mut self.lines, self.rock_pos - 1))
+            && !self.rock.intersect(Self::window(
+                &mut self.lines,
+                self.rock_pos - 1,
+                self.rock.height(),
+            ))
         {
-            // println!("down");
             self.rock_pos -= 1;
+            self.n_turns += 1;
         } else {
-            self.snapshot();
-
-            // println!("rest");
-            let lines = Self::lines4(&mut self.lines, self.rock_pos);
+            let lines = Self::window(&mut self.lines, self.rock_pos, self.rock.height());
             self.rock.apply(lines);
             self.new_rock();
+            self.n_turns += 1;
+            self.record_snapshot();
         }
-        self.n_turns += 1;
     }
 
-    fn snapshot(&mut self) {
-        if self.period.is_some() {
-            return;
+    /// Flood fill from above the tower, going only sideways and down like a
+    /// rock would. Stops at the first row that can't be reached, or after
+    /// `SURFACE_LIMIT` rows (a column that never gets filled would otherwise
+    /// make every state unique).
+    fn surface(&self) -> Vec<u64> {
+        let full = u64::MAX >> (64 - self.width);
+        let mut surface = vec![];
+        let mut reach = full;
+        for i in (0..self.top()).rev() {
+            let free = !self.lines[i] & full;
+            reach &= free;
+            loop {
+                let next = (reach | reach << 1 | reach >> 1) & free;
+                if next == reach {
+                    break;
+                }
+                reach = next;
+            }
+            if reach == 0 || surface.len() == SURFACE_LIMIT {
+                break;
+            }
+            surface.push(reach);
         }
-        if self.top() < SNAPSHOT_SIZE {
+        surface
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            jet: self.n_turns % self.shifts.len(),
+            rock: self.n_rocks % self.rocks.len(),
+            surface: self.surface(),
+        }
+    }
+
+    fn record_snapshot(&mut self) {
+        if self.period.is_some() {
             return;
         }
-        let snapshot = Snapshot {
-            n_turns_mod: self.n_turns % ROCKS.len(),
-            n_rocks_mod: self.n_rocks % ROCKS.len(),
-            last_rows: self.lines[self.top() - SNAPSHOT_SIZE..self.top()]
-                .try_into()
-                .unwrap(),
-        };
-        if let Some((n_rocks, top)) = self.snapshots.get(&snapshot).cloned() {
+        let snapshot = self.snapshot();
+        if let Some((n_rocks, n_turns, top)) = self.snapshots.get(&snapshot).cloned() {
             self.period = Some(Period {
                 n_rocks,
                 n_rocks_delta: self.n_rocks - n_rocks,
+                n_turns_delta: self.n_turns - n_turns,
                 top_delta: self.top() - top,
+                surface_depth: snapshot.surface.len(),
             })
         } else {
-            self.snapshots.insert(snapshot, (self.n_rocks, self.top()));
+            self.snapshots
+                .insert(snapshot, (self.n_rocks, self.n_turns, self.top()));
         }
     }
 
@@ -189,9 +280,8 @@ impl Well {
 
     fn new_rock(&mut self) {
         self.n_rocks += 1;
-        self.rock = ROCKS[self.n_rocks % ROCKS.len()].clone();
+        self.rock = self.rocks[self.n_rocks % self.rocks.len()].clone();
         self.rock_pos = self.top() + 3;
-        // println!("rock {}, top {}", self.n_rocks, self.top());
     }
 
     fn simulate(&mut self, n_rocks: usize) {
@@ -200,58 +290,117 @@ impl Well {
         }
     }
 
-    fn find_period(&mut self) -> Period {
+    /// Find a repeating state, then simulate one more period to check that it
+    /// really repeats with the same height gain.
+    fn find_period(&mut self) -> Result<Period> {
         while self.period.is_none() {
             self.tick();
         }
-        self.period.to_owned().unwrap()
+        let period = self.period.unwrap();
+        let snapshot = self.snapshot();
+        let top = self.top();
+        self.simulate(self.n_rocks + period.n_rocks_delta);
+        if self.snapshot() != snapshot || self.top() - top != period.top_delta {
+            return Err(eyre!("period did not repeat: {}", period.report()));
+        }
+        Ok(period)
     }
 
-    #[allow(dead_code)]
-    fn dump(&self, n: usize) {
-        let top = self.top();
-        for i in 0..n {
-            for j in (0..7).rev() {
-                let cell = self.lines[top - i - 1] & (1 << j) != 0;
-                print!("{}", if cell { '#' } else { '.' });
+    /// Draw the top `n` rows (or the whole well) in the puzzle's notation,
+    /// with the falling rock shown as `@`.
+    fn render(&self, n: Option<usize>) -> String {
+        let height = self.top().max(self.rock_pos + self.rock.height());
+        let bottom = n.map_or(0, |n| height.saturating_sub(n));
+        let mut s = String::new();
+        for i in (bottom..height).rev() {
+            let line = self.lines.get(i).copied().unwrap_or(0);
+            let rock = match i.checked_sub(self.rock_pos) {
+                Some(j) if j < self.rock.height() => self.rock.lines[j],
+                _ => 0,
+            };
+            s.push('|');
+            for j in (0..self.width).rev() {
+                s.push(if rock & (1 << j) != 0 {
+                    '@'
+                } else if line & (1 << j) != 0 {
+                    '#'
+                } else {
+                    '.'
+                });
             }
-            println!();
+            s.push_str("|\n");
         }
+        if bottom == 0 {
+            s.push('+');
+            s.push_str(&"-".repeat(self.width));
+            s.push_str("+\n");
+        }
+        s
     }
 }
 
-pub fn aoc_17() -> Result<(usize, usize)> {
+/// Usage: `17 [-w WIDTH] [-r ROCKS_FILE] [-t TRACE_ROCKS] [TARGET...]`
+///
+/// Returns the tower height after 2022 and 10^12 rocks, and also prints it
+/// after each TARGET number of rocks. With `-t`, the well is drawn after every
+/// step of the first TRACE_ROCKS rocks.
+pub fn aoc_17(args: &[String]) -> Result<(usize, usize)> {
+    let mut width = DEFAULT_WIDTH;
+    let mut rocks_desc = DEFAULT_ROCKS.to_owned();
+    let mut trace = 0;
+    let mut targets = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(eyre!("missing value for {}", arg));
+        match arg.as_str() {
+            "-w" => width = value()?.parse()?,
+            "-r" => rocks_desc = std::fs::read_to_string(value()?)?,
+            "-t" => trace = value()?.parse()?,
+            _ => targets.push(arg.parse()?),
+        }
+    }
+    let rocks = Rock::parse_all(&rocks_desc, width)?;
+
     let mut s = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
     let shifts = s
         .chars()
-        .into_iter()
         .filter_map(|c| match c {
             '<' => Some(Shift::Left),
             '>' => Some(Shift::Right),
             _ => None,
         })
         .collect_vec();
+    if shifts.is_empty() {
+        return Err(eyre!("no jets"));
+    }
 
-    dbg!(shifts.len());
-
-    let mut well = Well::new(shifts.clone());
-    well.simulate(2022);
-    let result = well.top();
-
-    let period = well.find_period();
-    dbg!(period);
-
-    let goal = 1000000000000;
+    if trace > 0 {
+        let mut well = Well::new(width, shifts.clone(), rocks.clone());
+        while well.n_rocks < trace {
+            println!("{}", well.render(None));
+            well.tick();
+        }
+        println!("{}", well.render(None));
+    }
 
-    let mut well = Well::new(shifts.clone());
-    well.simulate(period.n_rocks + (goal - period.n_rocks) % period.n_rocks_delta);
-    let result2 = well.top() + (goal - period.n_rocks) / period.n_rocks_delta * period.top_delta;
+    let period = Well::new(width, shifts.clone(), rocks.clone()).find_period()?;
+    eprintln!("{}", period.report());
 
-    // let mut well = Well::new(shifts.clone());
-    // well.simulate_until_period();
-    // dbg!(n0, t0, n1, t1);
+    let height_after = |goal: usize| {
+        let mut well = Well::new(width, shifts.clone(), rocks.clone());
+        if goal <= period.n_rocks {
+            well.simulate(goal);
+            well.top()
+        } else {
+            well.simulate(period.n_rocks + (goal - period.n_rocks) % period.n_rocks_delta);
+            well.top() + (goal - period.n_rocks) / period.n_rocks_delta * period.top_delta
+        }
+    };
+    for goal in targets {
+        println!("{} rocks: {}", goal, height_after(goal));
+    }
 
-    Ok((result, result2))
+    Ok((height_after(TARGETS[0]), height_after(TARGETS[1])))
 }
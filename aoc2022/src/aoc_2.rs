@@ -1,79 +1,159 @@
 use std::io::{BufRead, stdin};
 use std::error;
-use regex::Regex;
-use lazy_static::lazy_static;
+use itertools::Itertools;
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-#[derive(Debug, Clone, Copy)]
-enum Move {R, P, S}
-#[derive(Debug, Clone, Copy)]
-enum Outcome {Win, Lose, Draw}
+type Shape = usize;
 
-fn parse_line(s: &str) -> Option<(Move, Move)> {
-    use Move::*;
-    lazy_static! {
-        static ref MOVE_RE: Regex = Regex::new(r"^([ABC]) ([XYZ])$").unwrap();
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {Lose, Draw, Win}
 
-    let cap = MOVE_RE.captures(s)?;
-    let opponent = match cap.get(1).unwrap().as_str() {
-        "A" => R,
-        "B" => P,
-        "C" => S,
-        _ => unreachable!()
-    };
-    let me = match cap.get(2).unwrap().as_str() {
-        "X" => R,
-        "Y" => P,
-        "Z" => S,
-        _ => unreachable!()
-    };
-    Some((opponent, me))
+/// A game of cyclic dominance with an odd number of shapes: each shape beats
+/// the half of the other shapes that come right before it (wrapping around).
+#[derive(Debug)]
+struct Game {
+    names: Vec<&'static str>,
+    shape_scores: Vec<u64>,
+    /// Score for losing, drawing and winning.
+    outcome_scores: [u64; 3],
+    /// Letters the opponent uses for each shape.
+    opponent_letters: Vec<char>,
+    /// Letters in the second column, in the order of the shapes they stand
+    /// for in part 1.
+    my_letters: Vec<char>,
+    /// Second column letters read as lose, draw and win in part 2. Rounds
+    /// with other letters have no part 2 score.
+    outcome_letters: [char; 3],
 }
 
-fn fight(opponent: Move, me: Move) -> u64 {
-    use Move::*;
-    let score_fight = match (opponent, me) {
-        (R, P) | (P, S) | (S, R) => 6,
-        (P, R) | (S, P) | (R, S) => 0,
-        (R, R) | (P, P) | (S, S) => 3,
-    };
-    let score_shape = match me {
-        R => 1, P => 2, S => 3
-    };
-    score_fight + score_shape
-}
+impl Game {
+    fn rps() -> Self {
+        Game {
+            names: vec!["Rock", "Paper", "Scissors"],
+            shape_scores: vec![1, 2, 3],
+            outcome_scores: [0, 3, 6],
+            opponent_letters: vec!['A', 'B', 'C'],
+            my_letters: vec!['X', 'Y', 'Z'],
+            outcome_letters: ['X', 'Y', 'Z'],
+        }
+    }
+
+    fn rpsls() -> Self {
+        Game {
+            names: vec!["Rock", "Spock", "Paper", "Lizard", "Scissors"],
+            shape_scores: vec![1, 2, 3, 4, 5],
+            outcome_scores: [0, 3, 6],
+            opponent_letters: vec!['A', 'B', 'C', 'D', 'E'],
+            my_letters: vec!['V', 'W', 'X', 'Y', 'Z'],
+            outcome_letters: ['X', 'Y', 'Z'],
+        }
+    }
+
+    fn n_shapes(&self) -> usize {
+        self.names.len()
+    }
+
+    fn outcome(&self, opponent: Shape, me: Shape) -> Outcome {
+        let n = self.n_shapes();
+        match (me + n - opponent) % n {
+            0 => Outcome::Draw,
+            d if d <= n / 2 => Outcome::Win,
+            _ => Outcome::Lose,
+        }
+    }
+
+    fn fight(&self, opponent: Shape, me: Shape) -> u64 {
+        self.outcome_scores[self.outcome(opponent, me) as usize] + self.shape_scores[me]
+    }
+
+    /// Best-scoring shape that gives the outcome.
+    fn move_for_outcome(&self, opponent: Shape, outcome: Outcome) -> Shape {
+        (0..self.n_shapes())
+            .filter(|me| self.outcome(opponent, *me) == outcome)
+            .max_by_key(|me| self.shape_scores[*me])
+            .unwrap()
+    }
 
-fn move_for_outcome(opponent: Move, outcome: Outcome) -> Move {
-    use Move::*;
-    use Outcome::*;
-    match (opponent, outcome) {
-        (S, Win) | (R, Draw) | (P, Lose) => R,
-        (R, Win) | (P, Draw) | (S, Lose) => P,
-        (P, Win) | (S, Draw) | (R, Lose) => S,
+    fn parse_line(&self, s: &str) -> Option<(Shape, char)> {
+        let (a, b) = s.split_once(' ')?;
+        let (a, b) = (a.chars().exactly_one().ok()?, b.chars().exactly_one().ok()?);
+        let opponent = self.opponent_letters.iter().position(|c| *c == a)?;
+        if !self.my_letters.contains(&b) {
+            return None;
+        }
+        Some((opponent, b))
+    }
+
+    /// Total score if the second column letters stand for `shapes`.
+    fn score_with(&self, rounds: &[(Shape, char)], shapes: &[Shape]) -> u64 {
+        rounds.iter().map(|(opponent, letter)| {
+            let i = self.my_letters.iter().position(|c| c == letter).unwrap();
+            self.fight(*opponent, shapes[i])
+        }).sum()
+    }
+
+    /// Total score if the second column gives the outcome, or `None` if some
+    /// letter doesn't stand for one.
+    fn score_outcomes(&self, rounds: &[(Shape, char)]) -> Option<u64> {
+        let mut score = 0;
+        for (opponent, letter) in rounds {
+            let outcome = match self.outcome_letters.iter().position(|c| c == letter)? {
+                0 => Outcome::Lose,
+                1 => Outcome::Draw,
+                _ => Outcome::Win,
+            };
+            score += self.fight(*opponent, self.move_for_outcome(*opponent, outcome));
+        }
+        Some(score)
     }
 }
 
-pub fn aoc_2() -> Result<(u64, u64)> {
-    let mut score1: u64 = 0;
-    let mut score2: u64 = 0;
+/// Usage: `2 [rps|rpsls] [-a]`. With `-a`, print the part 1 total for every
+/// way of assigning shapes to the second column letters. If the second column
+/// has letters other than the outcome letters, a note is printed and part 2
+/// is 0.
+pub fn aoc_2(args: &[String]) -> Result<(u64, u64)> {
+    let mut game = Game::rps();
+    let mut all = false;
+    for arg in args {
+        match arg.as_str() {
+            "rps" => game = Game::rps(),
+            "rpsls" => game = Game::rpsls(),
+            "-a" => all = true,
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
 
+    let mut rounds = vec![];
     let stdin = stdin();
     for line_res in stdin.lock().lines() {
         let line = line_res?;
-        if let Some((opponent, me)) = parse_line(&line) {
-            use Move::*;
-            use Outcome::*;
-
-            score1 += fight(opponent, me);
-            let outcome = match me {R => Lose, P => Draw, S => Win};
-            let me2 = move_for_outcome(opponent, outcome);
-            score2 += fight(opponent, me2);
+        if let Some(round) = game.parse_line(&line) {
+            rounds.push(round);
         } else {
             return Err(format!("parse error: {}", &line).into())
         }
     }
 
+    if all {
+        for shapes in (0..game.n_shapes()).permutations(game.n_shapes()) {
+            let mapping = game.my_letters.iter().zip(shapes.iter())
+                .map(|(c, shape)| format!("{}={}", c, game.names[*shape]))
+                .join(" ");
+            println!("{}: {}", mapping, game.score_with(&rounds, &shapes));
+        }
+    }
+
+    let identity: Vec<Shape> = (0..game.n_shapes()).collect();
+    let score1 = game.score_with(&rounds, &identity);
+    let score2 = match game.score_outcomes(&rounds) {
+        Some(score) => score,
+        None => {
+            println!("no part 2: the second column has letters without an outcome");
+            0
+        }
+    };
+
     Ok((score1, score2))
 }
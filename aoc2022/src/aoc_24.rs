@@ -1,24 +1,10 @@
 use anyhow::anyhow;
 use std::{
-    collections::{HashSet, VecDeque},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
     io::{stdin, Read},
 };
 
-#[derive(Debug, Copy, Clone)]
-enum Direction {
-    U,
-    D,
-    L,
-    R,
-}
-
-#[derive(Debug)]
-struct Blizzard {
-    x: usize,
-    y: usize,
-    dir: Direction,
-}
-
 fn gcd(x: usize, y: usize) -> usize {
     assert!(x > 0);
     assert!(y > 0);
@@ -33,102 +19,128 @@ fn gcd(x: usize, y: usize) -> usize {
     x
 }
 
-impl Blizzard {
-    fn pos(&self, t: usize, w: usize, h: usize) -> (usize, usize) {
-        let w0 = w - 2;
-        let w0_t = w0 - t % w0;
-        let h0 = h - 2;
-        let h0_t = h0 - t % h0;
-        match self.dir {
-            Direction::U => (self.x, (self.y - 1 + h0_t) % h0 + 1),
-            Direction::D => (self.x, (self.y - 1 + t) % h0 + 1),
-            Direction::L => ((self.x - 1 + w0_t) % w0 + 1, self.y),
-            Direction::R => ((self.x - 1 + t) % w0 + 1, self.y),
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Move {
+    Wait,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Move {
+    const ALL: [Move; 5] = [Move::Wait, Move::Up, Move::Down, Move::Left, Move::Right];
+
+    fn apply(self, x: usize, y: usize, w: usize, h: usize) -> Option<(usize, usize)> {
+        match self {
+            Move::Wait => Some((x, y)),
+            Move::Up if y > 0 => Some((x, y - 1)),
+            Move::Down if y < h - 1 => Some((x, y + 1)),
+            Move::Left if x > 0 => Some((x - 1, y)),
+            Move::Right if x < w - 1 => Some((x + 1, y)),
+            _ => None,
         }
     }
 }
 
+#[derive(Debug)]
+struct Leg {
+    start: (usize, usize),
+    end: (usize, usize),
+    t0: usize,
+    t1: usize,
+    moves: Vec<Move>,
+}
+
+/// The basin as bit rows (bit `x` is column `x`). Blizzards never change
+/// direction, so each direction is stored as its starting masks and moved by
+/// rotating rows (horizontal blizzards) or picking another row (vertical ones).
 struct Field {
     w: usize,
     h: usize,
     period: usize,
-    blizzards: Vec<Blizzard>,
-    template: Vec<bool>,
-    slices: Vec<Vec<bool>>,
+    walls: Vec<u128>,
+    /// Blizzards per direction, one mask per inner row, bit `x - 1` for
+    /// column `x`.
+    up: Vec<u128>,
+    down: Vec<u128>,
+    left: Vec<u128>,
+    right: Vec<u128>,
 }
 
 impl Field {
     fn try_from_string(s: &str) -> anyhow::Result<Self> {
         let lines: Vec<&str> = s.lines().collect();
-        if lines.len() == 0 {
+        if lines.is_empty() {
             return Err(anyhow!("no first line"));
         }
 
         let w = lines[0].len();
         let h = lines.len();
-        let mut blizzards = Vec::new();
-        let mut template = Vec::new();
+        if !(3..=128).contains(&w) || h < 3 {
+            return Err(anyhow!("unsupported field size: {}x{}", w, h));
+        }
+        let mut walls = vec![0; h];
+        let mut up = vec![0; h - 2];
+        let mut down = vec![0; h - 2];
+        let mut left = vec![0; h - 2];
+        let mut right = vec![0; h - 2];
         for (y, line) in lines.iter().enumerate() {
             if line.len() != w {
                 return Err(anyhow!("unexpected line length: {}", line.len()));
             }
             for (x, c) in line.chars().enumerate() {
-                if c == '\n' {
-                    break;
-                }
-                template.push(c != '#');
-                let dir = match c {
-                    '^' => Some(Direction::U),
-                    'v' => Some(Direction::D),
-                    '<' => Some(Direction::L),
-                    '>' => Some(Direction::R),
-                    '#' | '.' => None,
+                let masks = match c {
+                    '#' => {
+                        walls[y] |= 1 << x;
+                        continue;
+                    }
+                    '.' => continue,
+                    '^' => &mut up,
+                    'v' => &mut down,
+                    '<' => &mut left,
+                    '>' => &mut right,
                     _ => return Err(anyhow!("unexpected character: {}", c)),
                 };
-                if let Some(dir) = dir {
-                    blizzards.push(Blizzard { x, y, dir });
+                if y == 0 || y == h - 1 || x == 0 || x == w - 1 {
+                    return Err(anyhow!("blizzard outside the basin at {}, {}", x, y));
                 }
+                masks[y - 1] |= 1 << (x - 1);
             }
         }
 
-        assert!(template.len() == w * h);
-
         Ok(Field {
             w,
             h,
             period: (w - 2) * (h - 2) / gcd(w - 2, h - 2),
-            blizzards,
-            slices: Vec::new(),
-            template,
+            walls,
+            up,
+            down,
+            left,
+            right,
         })
     }
 
-    fn get(&mut self, x: usize, y: usize, t: usize) -> bool {
-        assert!(x <= self.w);
-        assert!(y <= self.h);
-        let idx = y * self.w + x;
-        let slice = self.get_slice(t);
-        return slice[idx];
-    }
-
-    fn get_slice(&mut self, t: usize) -> &Vec<bool> {
-        let t = t % self.period;
-        while self.slices.len() <= t {
-            let slice = self.compute_slice(self.slices.len());
-            self.slices.push(slice);
+    /// Cells of row `y` that are blocked at time `t`.
+    fn blocked_row(&self, y: usize, t: usize) -> u128 {
+        if y == 0 || y == self.h - 1 {
+            return self.walls[y];
         }
-        &self.slices[t]
+        let (w0, h0) = (self.w - 2, self.h - 2);
+        let mask = (1 << w0) - 1;
+        let rotate = |row: u128, k: usize| ((row << k) | (row >> (w0 - k))) & mask;
+        let (i, tw, th) = (y - 1, t % w0, t % h0);
+        let blizzards = rotate(self.right[i], tw)
+            | rotate(self.left[i], (w0 - tw) % w0)
+            | self.down[(i + h0 - th) % h0]
+            | self.up[(i + th) % h0];
+        self.walls[y] | (blizzards << 1)
     }
 
-    fn compute_slice(&mut self, t: usize) -> Vec<bool> {
-        let mut slice = self.template.clone();
-        for blizzard in self.blizzards.iter() {
-            let (x, y) = blizzard.pos(t, self.w, self.h);
-            // dbg!(&blizzard, t, x, y);
-            // println!();
-            slice[y * self.w + x] = false;
-        }
-        slice
+    fn get(&self, x: usize, y: usize, t: usize) -> bool {
+        assert!(x < self.w);
+        assert!(y < self.h);
+        self.blocked_row(y, t) & (1 << x) == 0
     }
 
     fn start(&self) -> (usize, usize) {
@@ -139,49 +151,44 @@ impl Field {
         (self.w - 2, self.h - 1)
     }
 
+    /// A* search over (x, y, t), with Manhattan distance to the goal as the
+    /// heuristic. Occupancy comes from the blizzard bit rows, and states are
+    /// deduplicated modulo the blizzard period.
     fn traverse(
-        &mut self,
+        &self,
         t0: usize,
         start: (usize, usize),
         end: (usize, usize),
-    ) -> anyhow::Result<usize> {
-        let mut queue = VecDeque::new();
-        let mut seen = HashSet::new();
-
-        queue.push_back((start.0, start.1, t0));
-        while !queue.is_empty() {
-            let (x, y, t) = queue.pop_front().unwrap();
-            // println!("pop {}, {}, {}", x, y, t);
-
-            let seen_item = (x, y, t % self.period);
-            if seen.contains(&seen_item) {
-                // println!("  seen");
-                continue;
-            }
-
-            seen.insert(seen_item);
-
-            if !self.get(x, y, t) {
-                // println!("  not passable");
-                continue;
-            }
+    ) -> anyhow::Result<Leg> {
+        let dist = |(x, y): (usize, usize)| x.abs_diff(end.0) + y.abs_diff(end.1);
+        let mut queue = BinaryHeap::new();
+        let mut prev: HashMap<(usize, usize, usize), Move> = HashMap::new();
 
+        if self.get(start.0, start.1, t0) {
+            queue.push(Reverse((t0 + dist(start), t0, start.0, start.1)));
+            prev.insert((start.0, start.1, t0 % self.period), Move::Wait);
+        }
+        while let Some(Reverse((_, t, x, y))) = queue.pop() {
             if (x, y) == end {
-                return Ok(t);
+                return Ok(Leg {
+                    start,
+                    end,
+                    t0,
+                    t1: t,
+                    moves: self.reconstruct(&prev, t0, (x, y, t)),
+                });
             }
 
-            queue.push_back((x, y, t + 1));
-            if x > 0 {
-                queue.push_back((x - 1, y, t + 1));
-            }
-            if x < self.w - 1 {
-                queue.push_back((x + 1, y, t + 1));
-            }
-            if y > 0 {
-                queue.push_back((x, y - 1, t + 1));
-            }
-            if y < self.h - 1 {
-                queue.push_back((x, y + 1, t + 1));
+            for m in Move::ALL {
+                let Some((nx, ny)) = m.apply(x, y, self.w, self.h) else {
+                    continue;
+                };
+                let key = (nx, ny, (t + 1) % self.period);
+                if prev.contains_key(&key) || !self.get(nx, ny, t + 1) {
+                    continue;
+                }
+                prev.insert(key, m);
+                queue.push(Reverse((t + 1 + dist((nx, ny)), t + 1, nx, ny)));
             }
         }
 
@@ -193,8 +200,40 @@ impl Field {
         ))
     }
 
+    fn reconstruct(
+        &self,
+        prev: &HashMap<(usize, usize, usize), Move>,
+        t0: usize,
+        (mut x, mut y, t1): (usize, usize, usize),
+    ) -> Vec<Move> {
+        let mut moves = vec![];
+        for t in (t0 + 1..=t1).rev() {
+            let m = prev[&(x, y, t % self.period)];
+            moves.push(m);
+            (x, y) = match m {
+                Move::Wait => (x, y),
+                Move::Up => (x, y + 1),
+                Move::Down => (x, y - 1),
+                Move::Left => (x + 1, y),
+                Move::Right => (x - 1, y),
+            };
+        }
+        moves.reverse();
+        moves
+    }
+
+    /// Visit the waypoints in order, starting at time `t0`.
+    fn itinerary(&self, t0: usize, waypoints: &[(usize, usize)]) -> anyhow::Result<Vec<Leg>> {
+        let mut legs: Vec<Leg> = vec![];
+        for (start, end) in waypoints.iter().zip(waypoints.iter().skip(1)) {
+            let t = legs.last().map_or(t0, |leg| leg.t1);
+            legs.push(self.traverse(t, *start, *end)?);
+        }
+        Ok(legs)
+    }
+
     #[allow(dead_code)]
-    fn print_at(&mut self, t: usize) {
+    fn print_at(&self, t: usize) {
         println!("Field at t = {}:", t);
         for y in 0..self.h {
             for x in 0..self.w {
@@ -211,19 +250,25 @@ impl Field {
     }
 }
 
-pub fn aoc_24() -> anyhow::Result<(usize, usize)> {
+/// Usage: `24 [-v]`; with `-v`, print the moves of each leg.
+pub fn aoc_24(args: &[String]) -> anyhow::Result<(usize, usize)> {
     let mut s = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
-    let mut field = Field::try_from_string(&s)?;
+    let field = Field::try_from_string(&s)?;
     // dbg!(field.period);
     // field.print_at(0);
-    // field.print_at(1);
-    // field.print_at(2);
-
-    let t1 = field.traverse(0, field.start(), field.end())?;
-    let t2 = field.traverse(t1, field.end(), field.start())?;
-    let t3 = field.traverse(t2, field.start(), field.end())?;
-    // let result = 0;
-    Ok((t1, t3))
+
+    let (start, end) = (field.start(), field.end());
+    let legs = field.itinerary(0, &[start, end, start, end])?;
+    if args.first().map(String::as_str) == Some("-v") {
+        for leg in legs.iter() {
+            println!(
+                "{:?} -> {:?}, minutes {}..{}: {:?}",
+                leg.start, leg.end, leg.t0, leg.t1, leg.moves
+            );
+        }
+    }
+
+    Ok((legs[0].t1, legs[2].t1))
 }
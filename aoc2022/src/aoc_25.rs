@@ -1,49 +1,214 @@
 use anyhow::anyhow;
+use num_bigint::BigInt;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::io::{stdin, Read};
+use std::iter::Sum;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
 
-fn try_from_snafu(s: &str) -> anyhow::Result<isize> {
-    let mut result = 0;
-    for c in s.chars() {
-        let digit = match c {
-            '2' => 2,
-            '1' => 1,
-            '0' => 0,
-            '-' => -1,
-            '=' => -2,
-            _ => return Err(anyhow!("unrecognized digit: {}", c)),
-        };
-        result = result * 5 + digit;
-    }
-    Ok(result)
-}
-
-fn to_snafu(n: isize) -> String {
-    let mut chars = vec![];
-    let mut n = n;
-    while n != 0 {
-        let (digit, c) = match n % 5 {
-            0 => (0, '0'),
-            1 => (1, '1'),
-            2 => (2, '2'),
-            3 => (-2, '='),
-            4 => (-1, '-'),
-            _ => unreachable!(),
-        };
-        n = (n - digit) / 5;
-        chars.push(c);
-    }
-    chars.reverse();
-    String::from_iter(chars.into_iter())
-}
-
-pub fn aoc_25() -> anyhow::Result<String> {
+use crate::numeral::NumeralSystem;
+
+/// SNAFU number: balanced base 5 with digits `=`, `-`, `0`, `1`, `2`
+/// standing for -2..=2. Digits are stored least significant first, without
+/// leading zeros, so zero has no digits at all. Arithmetic works on the
+/// digits directly and never overflows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct Snafu {
+    digits: Vec<i8>,
+}
+
+impl Snafu {
+    /// Build a number from arbitrary per-position values, carrying until
+    /// every digit is in -2..=2.
+    fn normalize(values: &[i64]) -> Self {
+        let mut digits = vec![];
+        let mut carry = 0;
+        let mut i = 0;
+        while i < values.len() || carry != 0 {
+            let value = values.get(i).copied().unwrap_or(0) + carry;
+            let digit = (value + 2).rem_euclid(5) - 2;
+            carry = (value - digit) / 5;
+            digits.push(digit as i8);
+            i += 1;
+        }
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Snafu { digits }
+    }
+
+    fn signum(&self) -> i8 {
+        self.digits.last().map_or(0, |digit| digit.signum())
+    }
+}
+
+impl FromStr for Snafu {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if s.is_empty() {
+            return Err(anyhow!("empty number"));
+        }
+        let values = s
+            .chars()
+            .rev()
+            .map(|c| match c {
+                '2' => Ok(2),
+                '1' => Ok(1),
+                '0' => Ok(0),
+                '-' => Ok(-1),
+                '=' => Ok(-2),
+                _ => Err(anyhow!("unrecognized digit: {}", c)),
+            })
+            .collect::<anyhow::Result<Vec<i64>>>()?;
+        Ok(Self::normalize(&values))
+    }
+}
+
+impl Display for Snafu {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.digits.is_empty() {
+            return f.write_str("0");
+        }
+        let s: String = self
+            .digits
+            .iter()
+            .rev()
+            .map(|digit| match digit {
+                2 => '2',
+                1 => '1',
+                0 => '0',
+                -1 => '-',
+                -2 => '=',
+                _ => unreachable!(),
+            })
+            .collect();
+        f.write_str(&s)
+    }
+}
+
+impl From<i64> for Snafu {
+    fn from(n: i64) -> Self {
+        let mut digits = vec![];
+        let mut n = n as i128;
+        while n != 0 {
+            let digit = (n + 2).rem_euclid(5) - 2;
+            n = (n - digit) / 5;
+            digits.push(digit as i8);
+        }
+        Snafu { digits }
+    }
+}
+
+impl TryFrom<&Snafu> for i64 {
+    type Error = anyhow::Error;
+
+    fn try_from(n: &Snafu) -> anyhow::Result<Self> {
+        // Intermediate values can be just outside the i64 range even when the
+        // result is not, so accumulate in i128.
+        let value = n.digits.iter().rev().try_fold(0i128, |acc, digit| {
+            acc.checked_mul(5)?.checked_add(*digit as i128)
+        });
+        value
+            .and_then(|value| i64::try_from(value).ok())
+            .ok_or(anyhow!("SNAFU number out of range: {}", n))
+    }
+}
+
+impl Add for Snafu {
+    type Output = Snafu;
+
+    fn add(self, other: Snafu) -> Snafu {
+        let len = self.digits.len().max(other.digits.len());
+        let values: Vec<i64> = (0..len)
+            .map(|i| {
+                let a = self.digits.get(i).copied().unwrap_or(0);
+                let b = other.digits.get(i).copied().unwrap_or(0);
+                (a + b) as i64
+            })
+            .collect();
+        Snafu::normalize(&values)
+    }
+}
+
+impl Neg for Snafu {
+    type Output = Snafu;
+
+    fn neg(self) -> Snafu {
+        Snafu {
+            digits: self.digits.iter().map(|digit| -digit).collect(),
+        }
+    }
+}
+
+impl Sub for Snafu {
+    type Output = Snafu;
+
+    fn sub(self, other: Snafu) -> Snafu {
+        self + -other
+    }
+}
+
+impl Mul for Snafu {
+    type Output = Snafu;
+
+    fn mul(self, other: Snafu) -> Snafu {
+        let mut values = vec![0; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            for (j, b) in other.digits.iter().enumerate() {
+                values[i + j] += (a * b) as i64;
+            }
+        }
+        Snafu::normalize(&values)
+    }
+}
+
+impl Sum for Snafu {
+    fn sum<I: Iterator<Item = Snafu>>(iter: I) -> Snafu {
+        iter.fold(Snafu::default(), Add::add)
+    }
+}
+
+impl PartialOrd for Snafu {
+    fn partial_cmp(&self, other: &Snafu) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Snafu {
+    fn cmp(&self, other: &Snafu) -> Ordering {
+        // In balanced notation, the sign is the sign of the leading digit.
+        (self.clone() - other.clone()).signum().cmp(&0)
+    }
+}
+
+/// Usage: `25 [FROM TO]`. With numeral systems given (see
+/// `NumeralSystem::from_spec`), the input is read in FROM, each number is
+/// printed in TO, and so is the sum.
+pub fn aoc_25(args: &[String]) -> anyhow::Result<String> {
     let mut s = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
-    let nums: Result<Vec<isize>, _> = s.lines().map(|s| try_from_snafu(s)).collect();
-    let nums = nums?;
+    if let [from, to] = args {
+        let from = NumeralSystem::from_spec(from)?;
+        let to = NumeralSystem::from_spec(to)?;
+        let mut total = BigInt::default();
+        for line in s.lines() {
+            println!("{} -> {}", line, from.convert(line, &to)?);
+            total += from.parse(line)?;
+        }
+        return Ok(to.format(&total));
+    } else if !args.is_empty() {
+        return Err(anyhow!("expected: FROM TO"));
+    }
+
+    let nums = s
+        .lines()
+        .map(Snafu::from_str)
+        .collect::<anyhow::Result<Vec<Snafu>>>()?;
 
-    let result = to_snafu(nums.iter().sum());
+    let result: Snafu = nums.into_iter().sum();
 
-    Ok(result)
+    Ok(result.to_string())
 }
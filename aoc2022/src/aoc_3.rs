@@ -3,110 +3,115 @@ use std::error;
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-const N_ITEMS: usize = 52;
+const DEFAULT_GROUP_SIZE: usize = 3;
 
-fn get_index(b: u8) -> Option<usize> {
+/// Set of items, one bit per item: bit `priority - 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct ItemSet(u64);
+
+fn priority(b: u8) -> Option<usize> {
     match b {
-        b'a' ..= b'z' => Some((b - b'a') as usize),
-        b'A' ..= b'Z' => Some((b - b'A' + 26) as usize),
+        b'a' ..= b'z' => Some((b - b'a') as usize + 1),
+        b'A' ..= b'Z' => Some((b - b'A') as usize + 27),
         _ => None
     }
 }
 
-fn score_line_2(line: &str) -> Result<usize> {
-    let bytes = line.as_bytes();
-    let mut left: [bool; N_ITEMS] = [false; N_ITEMS];
-    let mut right: [bool; N_ITEMS] = [false; N_ITEMS];
-
-    if bytes.len() % 2 != 0 {
-        return Err("length not even".into());
+fn item(priority: usize) -> char {
+    match priority {
+        1 ..= 26 => (b'a' + (priority - 1) as u8) as char,
+        27 ..= 52 => (b'A' + (priority - 27) as u8) as char,
+        _ => panic!("no item with priority {}", priority),
     }
-    for i in 0..bytes.len() {
-        let b = bytes[i];
-        if let Some(index) = get_index(b) {
-            if i < bytes.len() / 2 {
-                left[index] = true;
-            } else {
-                right[index] = true;
-            }
-        } else {
-            return Err("unrecognized item".into());
+}
+
+impl ItemSet {
+    fn from_items(items: &[u8]) -> Result<Self> {
+        let mut set = ItemSet::default();
+        for b in items.iter().copied() {
+            let p = priority(b).ok_or(format!("unrecognized item: {:?}", b as char))?;
+            set.0 |= 1 << (p - 1);
         }
+        Ok(set)
     }
-    for item in (0..N_ITEMS).rev() {
-        if left[item] && right[item] {
-            return Ok(item + 1)
-        }
+
+    fn all() -> Self {
+        ItemSet((1 << 52) - 1)
     }
 
-    Err("no duplicate item found".into())
-}
+    fn intersection(self, other: ItemSet) -> Self {
+        ItemSet(self.0 & other.0)
+    }
 
-struct Check3 {
-    left: [bool; N_ITEMS],
-    mid: [bool; N_ITEMS],
-    right: [bool; N_ITEMS],
-    score: usize,
-}
+    fn union(self, other: ItemSet) -> Self {
+        ItemSet(self.0 | other.0)
+    }
 
-impl Check3 {
-    fn new() -> Self {
-        Self {
-            left: [false; N_ITEMS],
-            mid: [false; N_ITEMS],
-            right: [false; N_ITEMS],
-            score: 0,
-        }
+    /// Priorities of the items, lowest first.
+    fn priorities(self) -> impl Iterator<Item = usize> {
+        (1..=52).filter(move |p| self.0 & (1 << (p - 1)) != 0)
     }
 
-    fn add(&mut self, line: &str, i: usize) -> Result<()> {
-        let arr = match i % 3 {
-            0 => &mut self.left,
-            1 => &mut self.mid,
-            2 => &mut self.right,
-            _ => unreachable!()
-        };
-        for b in line.as_bytes() {
-            if let Some(index) = get_index(*b) {
-                arr[index] = true;
-            } else {
-                return Err("unrecognized item".into());
-            }
-        }
+    /// The shared item, by priority. If there are several, the highest wins.
+    fn shared(self) -> Option<usize> {
+        self.priorities().last()
+    }
+}
 
-        if i % 3 == 2 {
-            let mut score = None;
-            for item in (0..N_ITEMS).rev() {
-                if self.left[item] && self.mid[item] && self.right[item] {
-                    score = Some(item + 1);
-                    break;
-                }
-            }
-            if let Some(n) = score {
-                self.score += n;
-                self.left.fill(false);
-                self.mid.fill(false);
-                self.right.fill(false);
-            } else {
-                return Err("no triplicate item".into())
-            }
-        }
-        Ok(())
+fn score_line_2(line: &str) -> Result<usize> {
+    if !line.len().is_multiple_of(2) {
+        return Err("length not even".into());
     }
+    let (left, right) = line.as_bytes().split_at(line.len() / 2);
+    let common = ItemSet::from_items(left)?.intersection(ItemSet::from_items(right)?);
+    common.shared().ok_or_else(|| "no duplicate item found".into())
 }
 
-pub fn aoc_3() -> Result<(usize, usize)> {
+/// Usage: `3 [GROUP_SIZE]`. With GROUP_SIZE given, the item shared by each
+/// group is also printed.
+pub fn aoc_3(args: &[String]) -> Result<(usize, usize)> {
+    let group_size = match args.first() {
+        Some(arg) => arg.parse()?,
+        None => DEFAULT_GROUP_SIZE,
+    };
+    if group_size == 0 {
+        return Err("group size must be positive".into());
+    }
+
     let mut score: usize = 0;
-    let mut check3 = Check3::new();
-    let mut i = 0;
+    let mut group_score: usize = 0;
+    let mut group = ItemSet::all();
+    let mut seen = ItemSet::default();
+    let mut n_lines = 0;
 
     let stdin = stdin();
-    for line_res in stdin.lock().lines() {
+    for (i, line_res) in stdin.lock().lines().enumerate() {
         let line = line_res?;
-        score += score_line_2(&line)?;
-        check3.add(&line, i)?;
-        i += 1;
+        let n = i + 1;
+        score += score_line_2(&line).map_err(|err| format!("line {}: {}", n, err))?;
+
+        let items = ItemSet::from_items(line.as_bytes()).map_err(|err| format!("line {}: {}", n, err))?;
+        group = group.intersection(items);
+        seen = seen.union(items);
+        if n % group_size == 0 {
+            let p = group.shared().ok_or(format!(
+                "lines {}-{}: no item shared by the group", n + 1 - group_size, n
+            ))?;
+            if !args.is_empty() {
+                println!(
+                    "group {}: {} (priority {}, out of {} different items)",
+                    n / group_size, item(p), p, seen.priorities().count()
+                );
+            }
+            group_score += p;
+            group = ItemSet::all();
+            seen = ItemSet::default();
+        }
+        n_lines = n;
+    }
+    if n_lines % group_size != 0 {
+        return Err(format!("last group incomplete: {} lines", n_lines % group_size).into());
     }
 
-    Ok((score, check3.score))
+    Ok((score, group_score))
 }
@@ -1,4 +1,5 @@
 
+use std::fmt::{Display, Formatter};
 use std::io::{stdin, Read};
 
 use color_eyre::eyre::{eyre, Result};
@@ -8,42 +9,67 @@ use lazy_static::lazy_static;
 
 type Item = char;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Cargo {
     stacks: Vec<Vec<Item>>,
 }
 
 impl Cargo {
+    /// Parse the drawing: rows of crates, then the row of stack numbers,
+    /// which decides how many stacks there are.
     fn try_from_lines<'a, T>(lines: T)-> Result<Self>
         where T: Iterator<Item = &'a str>
     {
-        let mut cargo = Cargo{stacks: vec![]};
+        let mut lines: Vec<&str> = lines.collect();
+        let labels = lines.pop().ok_or(eyre!("empty drawing"))?;
+        let mut cargo = Cargo{stacks: vec![vec![]; Self::parse_labels(labels)?]};
         for line in lines {
-            cargo.add_line(&line)?;
+            cargo.add_line(line)?;
         }
         cargo.reverse();
         Ok(cargo)
     }
 
+    /// Position of the label (and of the crates) of stack `i`, counting from 0.
+    fn column(i: usize) -> usize {
+        4 * i + 1
+    }
+
+    /// Check that the label row is `1 2 3 ...`, each number starting in its
+    /// stack's column, and return the number of stacks.
+    fn parse_labels(line: &str) -> Result<usize> {
+        let mut n = 0;
+        let mut pos = 0;
+        for token in line.split(' ') {
+            if !token.is_empty() {
+                if token != (n + 1).to_string() || pos != Self::column(n) {
+                    return Err(eyre!("bad label row: {:?}", line));
+                }
+                n += 1;
+            }
+            pos += token.len() + 1;
+        }
+        if n == 0 {
+            return Err(eyre!("no stacks in label row: {:?}", line));
+        }
+        Ok(n)
+    }
+
     fn add_line(&mut self, line: &str) -> Result<()> {
         let chunks = line.chars().chunks(4);
         for (i, mut chunk) in chunks.into_iter().enumerate() {
-            match (chunk.next(), chunk.next(), chunk.next()) {
-                (Some(' '), Some(_), Some(' ')) => (),
-                (Some('['), Some(item), Some(']')) => self.add_item(i, item),
+            if i >= self.stacks.len() {
+                return Err(eyre!("more stacks than labels: {:?}", line));
+            }
+            match (chunk.next(), chunk.next(), chunk.next(), chunk.next()) {
+                (Some(' '), Some(' '), Some(' '), None | Some(' ')) => (),
+                (Some('['), Some(item), Some(']'), None | Some(' ')) => self.stacks[i].push(item),
                 _ => return Err(eyre!("unrecognized: {:?}", line)),
             }
         }
         Ok(())
     }
 
-    fn add_item(&mut self, i: usize, item: Item) {
-        if i >= self.stacks.len() {
-            self.stacks.resize(i + 1, vec![]);
-        }
-        self.stacks[i].push(item);
-    }
-
     fn reverse(&mut self) {
         for stack in &mut self.stacks {
             stack.reverse()
@@ -56,49 +82,269 @@ impl Cargo {
         ).collect()
     }
 
-    fn play_line(&mut self, line: &str, reverse: bool) -> Result<()> {
-        lazy_static! {
-            static ref MOVE_RE: Regex = Regex::new(r"move (\d+) from (\d+) to (\d+)$").unwrap();
-        }
-        let cap = MOVE_RE.captures(line).ok_or(eyre!("cannot parse"))?;
-        let n: usize = cap[1].parse()?;
-        let i: usize = cap[2].parse()?;
-        let j: usize = cap[3].parse()?;
-        self.play(n, i, j, reverse)?;
-        Ok(())
-    }
-
-    fn play(&mut self, n: usize, i: usize, j: usize, reverse: bool) -> Result<()> {
+    fn play(&mut self, mv: &Move, crane: &dyn Crane) -> Result<Vec<Item>> {
+        let (i, j) = (mv.from, mv.to);
         if i == 0 || j == 0 || i > self.stacks.len() || j > self.stacks.len() || i == j {
             return Err(eyre!("stack indexes wrong: {}, {}", i, j));
         }
         let m = self.stacks[i-1].len();
-        if n > m {
-            return Err(eyre!("amount too big: {}", n));
+        if mv.n > m {
+            return Err(eyre!("amount too big: {}", mv.n));
+        }
+        let lifted = self.stacks[i-1].split_off(m - mv.n);
+        let placed = crane.arrange(&lifted);
+        self.stacks[j-1].extend(placed);
+        Ok(lifted)
+    }
+
+    fn unplay(&mut self, mv: &Move, lifted: Vec<Item>) {
+        let m = self.stacks[mv.to-1].len();
+        self.stacks[mv.to-1].truncate(m - lifted.len());
+        self.stacks[mv.from-1].extend(lifted);
+    }
+}
+
+/// Draws the stacks exactly like the puzzle input, including trailing spaces
+/// and the label row.
+impl Display for Cargo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let line = self.stacks.iter().map(|stack| match stack.get(level) {
+                Some(item) => format!("[{}]", item),
+                None => "   ".to_owned(),
+            }).join(" ");
+            writeln!(f, "{}", line)?;
         }
-        for k in 0..n {
-            let idx = if reverse { m - k - 1 } else { m - n + k };
-            let c = self.stacks[i-1][idx];
-            self.stacks[j-1].push(c);
+        let mut labels = String::new();
+        for i in 0..self.stacks.len() {
+            labels.push_str(&" ".repeat(Self::column(i).saturating_sub(labels.len())));
+            labels.push_str(&(i + 1).to_string());
         }
-        self.stacks[i-1].truncate(m - n);
-        Ok(())
+        let width = Self::column(self.stacks.len()) - 2;
+        write!(f, "{:<width$}", labels, width = width)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Move {
+    n: usize,
+    from: usize,
+    to: usize,
+}
+
+impl Move {
+    fn parse(line: &str) -> Result<Self> {
+        lazy_static! {
+            static ref MOVE_RE: Regex = Regex::new(r"move (\d+) from (\d+) to (\d+)$").unwrap();
+        }
+        let cap = MOVE_RE.captures(line).ok_or(eyre!("cannot parse: {:?}", line))?;
+        Ok(Move {
+            n: cap[1].parse()?,
+            from: cap[2].parse()?,
+            to: cap[3].parse()?,
+        })
+    }
+}
+
+/// A crane model: decides the order in which the crates lifted from one stack
+/// land on the other.
+trait Crane {
+    /// Both `lifted` and the result are bottom-first.
+    fn arrange(&self, lifted: &[Item]) -> Vec<Item>;
+}
+
+/// Moves one crate at a time, reversing their order.
+struct CrateMover9000;
+
+/// Moves all the crates at once.
+struct CrateMover9001;
+
+/// Moves up to `capacity` crates at a time.
+struct LimitedCrane {
+    capacity: usize,
+}
+
+impl Crane for CrateMover9000 {
+    fn arrange(&self, lifted: &[Item]) -> Vec<Item> {
+        lifted.iter().rev().copied().collect()
+    }
+}
+
+impl Crane for CrateMover9001 {
+    fn arrange(&self, lifted: &[Item]) -> Vec<Item> {
+        lifted.to_vec()
     }
 }
 
-pub fn aoc_5() -> Result<(String, String)> {
+impl Crane for LimitedCrane {
+    fn arrange(&self, lifted: &[Item]) -> Vec<Item> {
+        lifted.rchunks(self.capacity).flatten().copied().collect()
+    }
+}
+
+/// A list of moves applied to a cargo, that can be stepped through and undone.
+struct Replay {
+    cargo: Cargo,
+    moves: Vec<Move>,
+    /// Crates lifted by each move done so far (bottom-first, as they were on
+    /// the source stack).
+    lifted: Vec<Vec<Item>>,
+}
+
+impl Replay {
+    fn new(cargo: Cargo, moves: Vec<Move>) -> Self {
+        Replay { cargo, moves, lifted: vec![] }
+    }
+
+    /// Do the next move. Returns false if all moves are done.
+    fn step(&mut self, crane: &dyn Crane) -> Result<bool> {
+        let k = self.lifted.len();
+        let Some(mv) = self.moves.get(k) else {
+            return Ok(false);
+        };
+        let lifted = self.cargo.play(mv, crane).map_err(|err| eyre!("move {}: {}", k + 1, err))?;
+        self.lifted.push(lifted);
+        Ok(true)
+    }
+
+    /// Undo the last move. Returns false if there is nothing to undo.
+    fn undo(&mut self) -> bool {
+        let Some(lifted) = self.lifted.pop() else {
+            return false;
+        };
+        let mv = self.moves[self.lifted.len()];
+        self.cargo.unplay(&mv, lifted);
+        true
+    }
+
+    fn run(&mut self, crane: &dyn Crane, verbose: bool) -> Result<String> {
+        while self.step(crane)? {
+            if verbose {
+                println!("after move {}:\n{}\n", self.lifted.len(), self.cargo);
+            }
+        }
+        self.cargo.tops()
+    }
+
+    fn rewind(&mut self) {
+        while self.undo() {}
+    }
+}
+
+/// Usage: `5 [-v] [CAPACITY]`. With `-v`, the stacks are drawn after every
+/// move. With CAPACITY, also print the tops for a crane that moves that many
+/// crates at a time.
+pub fn aoc_5(args: &[String]) -> Result<(String, String)> {
+    let mut verbose = false;
+    let mut capacity = None;
+    for arg in args {
+        match arg.as_str() {
+            "-v" => verbose = true,
+            _ => capacity = Some(arg.parse::<usize>()?),
+        }
+    }
+
     let mut s = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
     let (first, second) = s.split_once("\n\n").ok_or(eyre!("cannot split"))?;
-    let mut cargo1 = Cargo::try_from_lines(first.lines())?;
-    let mut cargo2 = cargo1.clone();
+    let cargo = Cargo::try_from_lines(first.lines())?;
+    let moves = second.lines().enumerate().map(
+        |(i, line)| Move::parse(line).map_err(|err| eyre!("move {}: {}", i + 1, err))
+    ).collect::<Result<Vec<Move>>>()?;
 
-    for line in second.lines() {
-        //dbg!(&cargo);
-        cargo1.play_line(line, true)?;
-        cargo2.play_line(line, false)?;
+    let mut replay = Replay::new(cargo, moves);
+    let result1 = replay.run(&CrateMover9000, verbose)?;
+    replay.rewind();
+    let result2 = replay.run(&CrateMover9001, verbose)?;
+    if let Some(capacity) = capacity {
+        if capacity == 0 {
+            return Err(eyre!("capacity must be positive"));
+        }
+        replay.rewind();
+        println!("capacity {}: {}", capacity, replay.run(&LimitedCrane { capacity }, verbose)?);
     }
 
-    Ok((cargo1.tops()?, cargo2.tops()?))
+    Ok((result1, result2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(lines: &[&str]) {
+        let drawing = lines.join("\n");
+        let cargo = Cargo::try_from_lines(drawing.lines()).unwrap();
+        assert_eq!(cargo.to_string(), drawing);
+    }
+
+    #[test]
+    fn round_trip_example() {
+        round_trip(&[
+            "    [D]    ",
+            "[N] [C]    ",
+            "[Z] [M] [P]",
+            " 1   2   3 ",
+        ]);
+    }
+
+    #[test]
+    fn round_trip_multi_digit_labels() {
+        round_trip(&[
+            "                                    [J]",
+            "[A] [B] [C] [D] [E] [F] [G] [H] [I] [J]",
+            " 1   2   3   4   5   6   7   8   9   10",
+        ]);
+    }
+
+    #[test]
+    fn round_trip_empty_stacks() {
+        round_trip(&[
+            "[A]        ",
+            "[B]     [C]",
+            " 1   2   3 ",
+        ]);
+        round_trip(&[" 1   2 "]);
+    }
+
+    /// Small xorshift generator, so that the cases are the same on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn round_trip_generated() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..1000 {
+            let n_stacks = 1 + rng.below(12);
+            let stacks = (0..n_stacks).map(|_| {
+                let height = rng.below(6);
+                (0..height).map(|_| (b'A' + rng.below(26) as u8) as char).collect()
+            }).collect();
+            let cargo = Cargo{stacks};
+            let drawing = cargo.to_string();
+            let parsed = Cargo::try_from_lines(drawing.lines()).unwrap();
+            assert_eq!(parsed, cargo);
+            assert_eq!(parsed.to_string(), drawing);
+        }
+    }
+
+    #[test]
+    fn misaligned_labels() {
+        assert!(Cargo::try_from_lines(["[A] [B]", "1   2 "].into_iter()).is_err());
+        assert!(Cargo::try_from_lines(["[A] [B]", " 1  2 "].into_iter()).is_err());
+    }
+
+    #[test]
+    fn more_crates_than_labels() {
+        assert!(Cargo::try_from_lines(["[A] [B] [C]", " 1   2 "].into_iter()).is_err());
+    }
 }
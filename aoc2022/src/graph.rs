@@ -0,0 +1,86 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Unweighted directed graph over named nodes. Names are interned to dense
+/// `u16` ids in order of first appearance.
+#[derive(Debug)]
+pub struct Graph<N> {
+    names: Vec<N>,
+    ids: HashMap<N, u16>,
+    edges: Vec<Vec<u16>>,
+}
+
+/// Distances between a chosen list of nodes, indexed by position in that list.
+#[derive(Debug)]
+pub struct DistanceMatrix {
+    size: usize,
+    distances: Vec<Option<usize>>,
+}
+
+impl<N: Copy + Eq + Hash> Graph<N> {
+    pub fn new() -> Self {
+        Self {
+            names: vec![],
+            ids: HashMap::new(),
+            edges: vec![],
+        }
+    }
+
+    pub fn intern(&mut self, name: N) -> u16 {
+        if let Some(id) = self.ids.get(&name) {
+            return *id;
+        }
+        let id = u16::try_from(self.names.len()).expect("too many nodes");
+        self.names.push(name);
+        self.ids.insert(name, id);
+        self.edges.push(vec![]);
+        id
+    }
+
+    pub fn id(&self, name: N) -> Option<u16> {
+        self.ids.get(&name).copied()
+    }
+
+    pub fn add_edge(&mut self, a: N, b: N) {
+        let a = self.intern(a);
+        let b = self.intern(b);
+        self.edges[a as usize].push(b);
+    }
+
+    /// Distances from `from` to every node, indexed by id.
+    pub fn bfs(&self, from: u16) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.names.len()];
+        let mut queue = VecDeque::new();
+        distances[from as usize] = Some(0);
+        queue.push_back(from);
+        while let Some(id) = queue.pop_front() {
+            let dist = distances[id as usize].unwrap();
+            for next in self.edges[id as usize].iter() {
+                if distances[*next as usize].is_none() {
+                    distances[*next as usize] = Some(dist + 1);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Compress the graph to the given nodes, running BFS from each of them.
+    pub fn distance_matrix(&self, ids: &[u16]) -> DistanceMatrix {
+        let mut distances = Vec::with_capacity(ids.len() * ids.len());
+        for from in ids {
+            let all = self.bfs(*from);
+            distances.extend(ids.iter().map(|to| all[*to as usize]));
+        }
+        DistanceMatrix {
+            size: ids.len(),
+            distances,
+        }
+    }
+}
+
+impl DistanceMatrix {
+    pub fn get(&self, i: usize, j: usize) -> Option<usize> {
+        self.distances[i * self.size + j]
+    }
+}
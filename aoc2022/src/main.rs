@@ -11,6 +11,10 @@ mod aoc_17;
 mod aoc_24;
 mod aoc_25;
 
+mod graph;
+mod numeral;
+mod top_k;
+
 use aoc_1::aoc_1;
 use aoc_2::aoc_2;
 use aoc_3::aoc_3;
@@ -28,22 +32,22 @@ fn main() {
     color_eyre::install().unwrap();
 
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("usage: {} <num>", &args[0]);
+    if args.len() < 2 {
+        println!("usage: {} <num> [args...]", &args[0]);
         std::process::exit(1);
     }
     match args[1].as_str() {
-        "1" => println!("{:?}", aoc_1().unwrap()),
-        "2" => println!("{:?}", aoc_2().unwrap()),
-        "3" => println!("{:?}", aoc_3().unwrap()),
-        "5" => println!("{:?}", aoc_5().unwrap()),
+        "1" => println!("{:?}", aoc_1(&args[2..]).unwrap()),
+        "2" => println!("{:?}", aoc_2(&args[2..]).unwrap()),
+        "3" => println!("{:?}", aoc_3(&args[2..]).unwrap()),
+        "5" => println!("{:?}", aoc_5(&args[2..]).unwrap()),
         "10" => println!("{:?}", aoc_10().unwrap()),
         "11" => println!("{:?}", aoc_11().unwrap()),
-        "14" => println!("{:?}", aoc_14().unwrap()),
-        "16" => println!("{:?}", aoc_16().unwrap()),
-        "17" => println!("{:?}", aoc_17().unwrap()),
-        "24" => println!("{:?}", aoc_24().unwrap()),
-        "25" => println!("{:?}", aoc_25().unwrap()),
+        "14" => println!("{:?}", aoc_14(&args[2..]).unwrap()),
+        "16" => println!("{:?}", aoc_16(&args[2..]).unwrap()),
+        "17" => println!("{:?}", aoc_17(&args[2..]).unwrap()),
+        "24" => println!("{:?}", aoc_24(&args[2..]).unwrap()),
+        "25" => println!("{:?}", aoc_25(&args[2..]).unwrap()),
         _ => {
             println!("unrecognized num: {}", &args[1]);
             std::process::exit(1);
@@ -0,0 +1,117 @@
+use anyhow::anyhow;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+
+/// Positional numeral system with a custom digit alphabet, listed in order of
+/// value starting from `min_digit`. Standard systems start at 0 and write
+/// negative numbers with a leading `-`; balanced systems have digits centred
+/// around 0 and need no sign.
+#[derive(Clone, Debug)]
+pub struct NumeralSystem {
+    alphabet: Vec<char>,
+    min_digit: i64,
+}
+
+impl NumeralSystem {
+    fn new(alphabet: &str, min_digit: i64) -> anyhow::Result<Self> {
+        let alphabet: Vec<char> = alphabet.chars().collect();
+        if alphabet.len() < 2 {
+            return Err(anyhow!("need at least two digits"));
+        }
+        for (i, c) in alphabet.iter().enumerate() {
+            if alphabet[..i].contains(c) {
+                return Err(anyhow!("duplicate digit: {}", c));
+            }
+        }
+        Ok(Self {
+            alphabet,
+            min_digit,
+        })
+    }
+
+    pub fn standard(alphabet: &str) -> anyhow::Result<Self> {
+        let system = Self::new(alphabet, 0)?;
+        if system.alphabet.contains(&'-') {
+            return Err(anyhow!("'-' is reserved for the sign"));
+        }
+        Ok(system)
+    }
+
+    /// Balanced system: the base has to be odd, e.g. `=-012` for SNAFU.
+    pub fn balanced(alphabet: &str) -> anyhow::Result<Self> {
+        let base = alphabet.chars().count() as i64;
+        if base % 2 == 0 {
+            return Err(anyhow!("balanced system needs an odd base, got {}", base));
+        }
+        Self::new(alphabet, -(base / 2))
+    }
+
+    /// Parse a system description: a known name (`snafu`, `bin`, `dec`,
+    /// `hex`), or `standard:DIGITS` / `balanced:DIGITS`.
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        match spec.split_once(':') {
+            Some(("standard", alphabet)) => Self::standard(alphabet),
+            Some(("balanced", alphabet)) => Self::balanced(alphabet),
+            None => match spec {
+                "snafu" => Self::balanced("=-012"),
+                "bin" => Self::standard("01"),
+                "dec" => Self::standard("0123456789"),
+                "hex" => Self::standard("0123456789abcdef"),
+                _ => Err(anyhow!("unknown numeral system: {}", spec)),
+            },
+            _ => Err(anyhow!("unknown numeral system: {}", spec)),
+        }
+    }
+
+    fn base(&self) -> i64 {
+        self.alphabet.len() as i64
+    }
+
+    fn digit_value(&self, c: char) -> anyhow::Result<i64> {
+        let pos = self
+            .alphabet
+            .iter()
+            .position(|d| *d == c)
+            .ok_or(anyhow!("unrecognized digit: {}", c))?;
+        Ok(pos as i64 + self.min_digit)
+    }
+
+    pub fn parse(&self, s: &str) -> anyhow::Result<BigInt> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) if self.min_digit == 0 => (true, rest),
+            _ => (false, s),
+        };
+        if digits.is_empty() {
+            return Err(anyhow!("empty number"));
+        }
+        let mut n = BigInt::zero();
+        for c in digits.chars() {
+            n = n * self.base() + self.digit_value(c)?;
+        }
+        Ok(if negative { -n } else { n })
+    }
+
+    pub fn format(&self, n: &BigInt) -> String {
+        if self.min_digit == 0 && n.is_negative() {
+            return format!("-{}", self.format(&-n));
+        }
+        let mut chars = vec![];
+        let mut n = n.clone();
+        while !n.is_zero() {
+            let digit =
+                (&n - self.min_digit).mod_floor(&BigInt::from(self.base())) + self.min_digit;
+            n = (n - &digit) / self.base();
+            let idx: i64 = (digit - self.min_digit).try_into().unwrap();
+            chars.push(self.alphabet[idx as usize]);
+        }
+        if chars.is_empty() {
+            chars.push(self.alphabet[(-self.min_digit) as usize]);
+        }
+        chars.iter().rev().collect()
+    }
+
+    pub fn convert(&self, s: &str, to: &NumeralSystem) -> anyhow::Result<String> {
+        Ok(to.format(&self.parse(s)?))
+    }
+}
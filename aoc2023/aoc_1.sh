@@ -1,3 +0,0 @@
-#!/bin/sh
-
-sed 's/[a-z]//g; s/^\([0-9]\)$/\1\1/g; s/\([0-9]\).*\([0-9]\)/\1\2/g' | paste -s -d+ - | bc
@@ -52,38 +52,85 @@ impl Universe {
         Ok(Universe { stars, rows, cols })
     }
 
-    fn distance(&self, i: usize, j: usize, empty_size: usize) -> usize {
-        let (x0, y0) = self.stars[i];
-        let (x1, y1) = self.stars[j];
-        let mut distance = x1.abs_diff(x0) + y1.abs_diff(y0);
-        for x in x0.min(x1) + 1..x0.max(x1) {
-            if !self.cols[x] {
-                distance += empty_size - 1;
-            }
-        }
-        for y in y0.min(y1) + 1..y0.max(y1) {
-            if !self.rows[y] {
-                distance += empty_size - 1;
+    /// Star coordinates once every empty row and column is `empty_size` wide.
+    fn expanded(&self, empty_size: usize) -> Vec<(usize, usize)> {
+        let offsets = |filled: &[bool]| -> Vec<usize> {
+            let mut empty = 0;
+            let mut offsets = vec![];
+            for (i, filled) in filled.iter().enumerate() {
+                offsets.push(i + empty * (empty_size - 1));
+                if !filled {
+                    empty += 1;
+                }
             }
-        }
-        distance
+            offsets
+        };
+        let xs = offsets(&self.cols);
+        let ys = offsets(&self.rows);
+        self.stars.iter().map(|&(x, y)| (xs[x], ys[y])).collect()
     }
 
+    /// Distance between galaxies `i` and `j`, numbered from 1 in reading order.
+    fn distance(&self, i: usize, j: usize, empty_size: usize) -> Result<usize> {
+        let stars = self.expanded(empty_size);
+        let star = |i: usize| {
+            i.checked_sub(1)
+                .and_then(|i| stars.get(i))
+                .ok_or(eyre!("no galaxy {i}"))
+        };
+        let ((x0, y0), (x1, y1)) = (star(i)?, star(j)?);
+        Ok(x1.abs_diff(*x0) + y1.abs_diff(*y0))
+    }
+
+    /// Sum of distances over all pairs, one axis at a time: once sorted, each
+    /// coordinate is added once per smaller coordinate and subtracted once per
+    /// larger one.
     pub fn all_distances(&self, empty_size: usize) -> usize {
-        let mut result = 0;
-        for i in 0..self.stars.len() - 1 {
-            for j in i + 1..self.stars.len() {
-                result += self.distance(i, j, empty_size);
+        let stars = self.expanded(empty_size);
+        let axis_sum = |mut coords: Vec<usize>| -> usize {
+            coords.sort_unstable();
+            let mut before = 0;
+            let mut result = 0;
+            for (k, c) in coords.iter().enumerate() {
+                result += c * k - before;
+                before += c;
             }
-        }
-        result
+            result
+        };
+        axis_sum(stars.iter().map(|(x, _)| *x).collect())
+            + axis_sum(stars.iter().map(|(_, y)| *y).collect())
     }
 }
 
-pub fn aoc_11() -> Result<(usize, usize)> {
+/// Usage: `11 [-e EMPTY_SIZE] [GALAXY GALAXY]`. With `-e`, also print the total
+/// distance when empty rows and columns are that wide; with two galaxy
+/// numbers, print the distance between them (for that size, or 2 by default).
+pub fn aoc_11(args: &[String]) -> Result<(usize, usize)> {
+    let (empty_size, galaxies) = match args {
+        [flag, size, rest @ ..] if flag == "-e" => (Some(size.parse::<usize>()?), rest),
+        rest => (None, rest),
+    };
+    if empty_size == Some(0) {
+        return Err(eyre!("empty size must be positive"));
+    }
+
     let mut s: String = "".to_owned();
     stdin().read_to_string(&mut s)?;
     let universe = Universe::parse(&s)?;
+
+    if let Some(empty_size) = empty_size {
+        println!("total: {}", universe.all_distances(empty_size));
+    }
+    match galaxies {
+        [] => {}
+        [i, j] => {
+            let (i, j) = (i.parse()?, j.parse()?);
+            let distance = universe.distance(i, j, empty_size.unwrap_or(2))?;
+            println!("distance between {i} and {j}: {distance}");
+        }
+        _ => return Err(eyre!("expected two galaxy numbers")),
+    }
+
     let part1 = universe.all_distances(2);
     let part2 = universe.all_distances(1_000_000);
     Ok((part1, part2))
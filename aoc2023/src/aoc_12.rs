@@ -0,0 +1,200 @@
+use color_eyre::eyre::{eyre, Result};
+use nom::{
+    bytes::complete::tag,
+    character::complete::one_of,
+    combinator::map,
+    multi::{many1, separated_list1},
+    sequence::{separated_pair, terminated},
+};
+
+use crate::util::{parse_stdin, parse_usize};
+
+const COPIES: usize = 5;
+
+/// Rows with more unknown springs than this are not enumerated.
+const MAX_ENUMERATED_UNKNOWNS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spring {
+    Operational,
+    Damaged,
+    Unknown,
+}
+
+impl Spring {
+    fn parse(c: char) -> Self {
+        match c {
+            '.' => Spring::Operational,
+            '#' => Spring::Damaged,
+            _ => Spring::Unknown,
+        }
+    }
+
+    fn can_be(self, state: Spring) -> bool {
+        self == state || self == Spring::Unknown
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Record {
+    springs: Vec<Spring>,
+    groups: Vec<usize>,
+}
+
+/// Progress through the groups: how many have been started, and how long the
+/// current run of damaged springs is (0 after an operational one).
+#[derive(Debug, Clone, Copy)]
+struct State {
+    group: usize,
+    run: usize,
+}
+
+impl Record {
+    fn parse(s: &str) -> nom::IResult<&str, Self> {
+        map(
+            separated_pair(
+                many1(map(one_of(".#?"), Spring::parse)),
+                tag(" "),
+                separated_list1(tag(","), parse_usize),
+            ),
+            |(springs, groups)| Record { springs, groups },
+        )(s)
+    }
+
+    fn unfold(&self, copies: usize) -> Self {
+        let mut springs = vec![];
+        for i in 0..copies {
+            if i > 0 {
+                springs.push(Spring::Unknown);
+            }
+            springs.extend_from_slice(&self.springs);
+        }
+        Record {
+            springs,
+            groups: self.groups.repeat(copies),
+        }
+    }
+
+    /// State after the next spring, if it is consistent with the groups.
+    fn next(&self, state: State, spring: Spring) -> Option<State> {
+        let State { group, run } = state;
+        match spring {
+            Spring::Operational if run == 0 || run == self.groups[group - 1] => {
+                Some(State { group, run: 0 })
+            }
+            Spring::Damaged if run == 0 && group < self.groups.len() => Some(State {
+                group: group + 1,
+                run: 1,
+            }),
+            Spring::Damaged if run > 0 && run < self.groups[group - 1] => Some(State {
+                group,
+                run: run + 1,
+            }),
+            _ => None,
+        }
+    }
+
+    fn is_done(&self, state: State) -> bool {
+        state.group == self.groups.len()
+            && (state.run == 0 || state.run == self.groups[state.group - 1])
+    }
+
+    /// Number of arrangements, going through the springs once and keeping a
+    /// count for each (group index, run length) state.
+    fn count(&self) -> u128 {
+        let max_run = self.groups.iter().copied().max().unwrap_or(0);
+        let mut counts = vec![vec![0u128; max_run + 1]; self.groups.len() + 1];
+        counts[0][0] = 1;
+        for spring in self.springs.iter() {
+            let mut next_counts = vec![vec![0u128; max_run + 1]; self.groups.len() + 1];
+            for (group, runs) in counts.iter().enumerate() {
+                for (run, n) in runs.iter().enumerate() {
+                    if *n == 0 {
+                        continue;
+                    }
+                    for state in [Spring::Operational, Spring::Damaged] {
+                        if !spring.can_be(state) {
+                            continue;
+                        }
+                        if let Some(next) = self.next(State { group, run }, state) {
+                            next_counts[next.group][next.run] += n;
+                        }
+                    }
+                }
+            }
+            counts = next_counts;
+        }
+        counts
+            .iter()
+            .enumerate()
+            .flat_map(|(group, runs)| {
+                runs.iter()
+                    .enumerate()
+                    .filter(move |(run, _)| self.is_done(State { group, run: *run }))
+                    .map(|(_, n)| *n)
+            })
+            .sum()
+    }
+
+    /// All arrangements, drawn with `.` and `#`.
+    fn arrangements(&self) -> Vec<String> {
+        let mut result = vec![];
+        self.enumerate(State { group: 0, run: 0 }, &mut String::new(), &mut result);
+        result
+    }
+
+    fn enumerate(&self, state: State, prefix: &mut String, result: &mut Vec<String>) {
+        let Some(spring) = self.springs.get(prefix.len()) else {
+            if self.is_done(state) {
+                result.push(prefix.clone());
+            }
+            return;
+        };
+        for (choice, c) in [(Spring::Operational, '.'), (Spring::Damaged, '#')] {
+            if let Some(next) = spring
+                .can_be(choice)
+                .then(|| self.next(state, choice))
+                .flatten()
+            {
+                prefix.push(c);
+                self.enumerate(next, prefix, result);
+                prefix.pop();
+            }
+        }
+    }
+}
+
+/// Usage: `12 [-v] [COPIES]`. Part 2 unfolds each record into COPIES copies
+/// (5 by default). With `-v`, print every arrangement of the folded rows that
+/// have at most 16 unknown springs.
+pub fn aoc_12(args: &[String]) -> Result<(u128, u128)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    let copies = match &args[verbose as usize..] {
+        [] => COPIES,
+        [copies] => copies.parse()?,
+        _ => return Err(eyre!("too many arguments")),
+    };
+    let records = parse_stdin(|s| many1(terminated(Record::parse, tag("\n")))(s))?;
+
+    let mut part1 = 0;
+    let mut part2 = 0;
+    for record in records.iter() {
+        let count = record.count();
+        if verbose {
+            let unknowns = record
+                .springs
+                .iter()
+                .filter(|s| **s == Spring::Unknown)
+                .count();
+            println!("{count} arrangements");
+            if unknowns <= MAX_ENUMERATED_UNKNOWNS {
+                for arrangement in record.arrangements() {
+                    println!("  {arrangement}");
+                }
+            }
+        }
+        part1 += count;
+        part2 += record.unfold(copies).count();
+    }
+    Ok((part1, part2))
+}
@@ -0,0 +1,146 @@
+use std::io::{stdin, Read};
+
+use color_eyre::{eyre::eyre, Result};
+
+/// A pattern of ash and rocks, one bit per rock, stored both by row (bit `x`
+/// of `rows[y]`) and by column (bit `y` of `cols[x]`).
+#[derive(Debug)]
+struct Pattern {
+    rows: Vec<u64>,
+    cols: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    /// Between a row and the next one, given as the number of rows above.
+    Horizontal(usize),
+    /// Between a column and the next one, given as the number of columns to
+    /// the left.
+    Vertical(usize),
+}
+
+impl Axis {
+    fn summary(self) -> usize {
+        match self {
+            Axis::Horizontal(n) => 100 * n,
+            Axis::Vertical(n) => n,
+        }
+    }
+}
+
+/// A reflection axis, with the (x, y) coordinates of the cells that differ
+/// from their mirror image. Each pair is reported once, by the cell above or
+/// to the left of the axis.
+#[derive(Debug)]
+struct Reflection {
+    axis: Axis,
+    smudges: Vec<(usize, usize)>,
+}
+
+/// Axes of `lines` where exactly `k` bits differ from their mirror image, and
+/// the (line, bit) position of each difference.
+fn mirror_axes(lines: &[u64], k: u32) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let mut result = vec![];
+    for axis in 1..lines.len() {
+        let pairs = (0..axis).rev().zip(axis..lines.len());
+        let diffs: u32 = pairs
+            .clone()
+            .map(|(a, b)| (lines[a] ^ lines[b]).count_ones())
+            .sum();
+        if diffs != k {
+            continue;
+        }
+        let mut smudges = vec![];
+        for (a, b) in pairs {
+            let diff = lines[a] ^ lines[b];
+            smudges.extend(
+                (0..64)
+                    .filter(|bit| diff & 1 << bit != 0)
+                    .map(|bit| (a, bit)),
+            );
+        }
+        result.push((axis, smudges));
+    }
+    result
+}
+
+impl Pattern {
+    fn parse(s: &str) -> Result<Self> {
+        let mut rows = vec![];
+        let mut cols: Vec<u64> = vec![];
+        for (y, line) in s.lines().enumerate() {
+            if y >= 64 || line.len() > 64 {
+                return Err(eyre!("pattern larger than 64x64"));
+            }
+            if cols.is_empty() {
+                cols.resize(line.len(), 0);
+            } else if line.len() != cols.len() {
+                return Err(eyre!("uneven line: {line}"));
+            }
+            let mut row = 0;
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => {
+                        row |= 1 << x;
+                        cols[x] |= 1 << y;
+                    }
+                    '.' => {}
+                    _ => return Err(eyre!("unknown character: {c:?}")),
+                }
+            }
+            rows.push(row);
+        }
+        if rows.is_empty() {
+            return Err(eyre!("empty pattern"));
+        }
+        Ok(Pattern { rows, cols })
+    }
+
+    /// All reflections with exactly `k` smudges, vertical ones first.
+    fn reflections(&self, k: u32) -> Vec<Reflection> {
+        let vertical = mirror_axes(&self.cols, k)
+            .into_iter()
+            .map(|(axis, smudges)| Reflection {
+                axis: Axis::Vertical(axis),
+                smudges,
+            });
+        let horizontal = mirror_axes(&self.rows, k)
+            .into_iter()
+            .map(|(axis, smudges)| Reflection {
+                axis: Axis::Horizontal(axis),
+                smudges: smudges.into_iter().map(|(y, x)| (x, y)).collect(),
+            });
+        vertical.chain(horizontal).collect()
+    }
+
+    fn summary(&self, k: u32) -> Result<usize> {
+        self.reflections(k)
+            .first()
+            .map(|reflection| reflection.axis.summary())
+            .ok_or(eyre!("no reflection with {k} smudges"))
+    }
+}
+
+/// Usage: `13 [-v]`. With `-v`, print every reflection with 0 or 1 smudge.
+pub fn aoc_13(args: &[String]) -> Result<(usize, usize)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    let mut s: String = "".to_owned();
+    stdin().read_to_string(&mut s)?;
+
+    let mut part1 = 0;
+    let mut part2 = 0;
+    for (i, block) in s.split("\n\n").enumerate() {
+        let pattern = Pattern::parse(block)?;
+        if verbose {
+            println!("pattern {}:", i + 1);
+            for k in [0, 1] {
+                for reflection in pattern.reflections(k) {
+                    println!("  {:?} smudges {:?}", reflection.axis, reflection.smudges);
+                }
+            }
+        }
+        part1 += pattern.summary(0)?;
+        part2 += pattern.summary(1)?;
+    }
+    Ok((part1, part2))
+}
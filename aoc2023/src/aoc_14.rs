@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::{stdin, Read};
+
+use color_eyre::{eyre::eyre, Result};
+
+const CYCLES: usize = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Cell {
+    Empty,
+    Cube,
+    Round,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    North,
+    West,
+    South,
+    East,
+}
+
+const SPIN: [Direction; 4] = [
+    Direction::North,
+    Direction::West,
+    Direction::South,
+    Direction::East,
+];
+
+#[derive(Debug, Clone)]
+struct Platform {
+    w: usize,
+    h: usize,
+    cells: Vec<Cell>,
+}
+
+/// Where a sequence of spin cycles starts repeating.
+#[derive(Debug)]
+struct Loop {
+    start: usize,
+    period: usize,
+}
+
+impl Platform {
+    fn parse(s: &str) -> Result<Self> {
+        let mut cells = vec![];
+        let mut w = 0;
+        let mut h = 0;
+        for line in s.lines() {
+            if h == 0 {
+                w = line.len();
+            } else if line.len() != w {
+                return Err(eyre!("uneven line: {line}"));
+            }
+            for c in line.chars() {
+                cells.push(match c {
+                    '.' => Cell::Empty,
+                    '#' => Cell::Cube,
+                    'O' => Cell::Round,
+                    _ => return Err(eyre!("unknown character: {c:?}")),
+                });
+            }
+            h += 1;
+        }
+        if h == 0 || w == 0 {
+            return Err(eyre!("empty platform"));
+        }
+        Ok(Platform { w, h, cells })
+    }
+
+    /// Index of the `k`-th cell of line `i`, counting from the edge the rocks
+    /// roll towards. Lines are columns when tilting north or south, and rows
+    /// otherwise.
+    fn index(&self, direction: Direction, i: usize, k: usize) -> usize {
+        match direction {
+            Direction::North => k * self.w + i,
+            Direction::South => (self.h - 1 - k) * self.w + i,
+            Direction::West => i * self.w + k,
+            Direction::East => i * self.w + self.w - 1 - k,
+        }
+    }
+
+    /// Roll every round rock as far as it goes, compacting each line against
+    /// the edge or the cube rock before it.
+    fn tilt(&mut self, direction: Direction) {
+        let (n_lines, len) = match direction {
+            Direction::North | Direction::South => (self.w, self.h),
+            Direction::West | Direction::East => (self.h, self.w),
+        };
+        for i in 0..n_lines {
+            let mut free = 0;
+            for k in 0..len {
+                let index = self.index(direction, i, k);
+                match self.cells[index] {
+                    Cell::Empty => {}
+                    Cell::Cube => free = k + 1,
+                    Cell::Round => {
+                        self.cells[index] = Cell::Empty;
+                        let target = self.index(direction, i, free);
+                        self.cells[target] = Cell::Round;
+                        free += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn spin_cycle(&mut self) {
+        for direction in SPIN {
+            self.tilt(direction);
+        }
+    }
+
+    /// Run `n` spin cycles, skipping ahead once a state repeats. With `dump`,
+    /// print the platform after each cycle actually simulated.
+    fn spin(&mut self, n: usize, dump: bool) -> Option<Loop> {
+        let mut seen: HashMap<Vec<Cell>, usize> = HashMap::new();
+        let mut history: Vec<Vec<Cell>> = vec![];
+        for i in 0..n {
+            if let Some(start) = seen.get(&self.cells).copied() {
+                let period = i - start;
+                self.cells = history[start + (n - start) % period].clone();
+                return Some(Loop { start, period });
+            }
+            seen.insert(self.cells.clone(), i);
+            history.push(self.cells.clone());
+            self.spin_cycle();
+            if dump {
+                println!(
+                    "after {} cycles (load {}):\n{}",
+                    i + 1,
+                    self.north_load(),
+                    self
+                );
+            }
+        }
+        None
+    }
+
+    fn north_load(&self) -> usize {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == Cell::Round)
+            .map(|(i, _)| self.h - i / self.w)
+            .sum()
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(self.w) {
+            for cell in row {
+                let c = match cell {
+                    Cell::Empty => '.',
+                    Cell::Cube => '#',
+                    Cell::Round => 'O',
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Usage: `14 [-v] [CYCLES]`. Part 2 runs CYCLES spin cycles (10^9 by
+/// default). With `-v`, print the platform after every simulated cycle,
+/// and where the states start repeating.
+pub fn aoc_14(args: &[String]) -> Result<(usize, usize)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    let cycles = match &args[verbose as usize..] {
+        [] => CYCLES,
+        [cycles] => cycles.parse()?,
+        _ => return Err(eyre!("too many arguments")),
+    };
+
+    let mut s: String = "".to_owned();
+    stdin().read_to_string(&mut s)?;
+    let platform = Platform::parse(&s)?;
+
+    let mut tilted = platform.clone();
+    tilted.tilt(Direction::North);
+    let part1 = tilted.north_load();
+
+    let mut spun = platform;
+    let repeat = spun.spin(cycles, verbose);
+    if let (true, Some(Loop { start, period })) = (verbose, repeat) {
+        println!("states repeat every {period} cycles after {start}");
+    }
+    let part2 = spun.north_load();
+    Ok((part1, part2))
+}
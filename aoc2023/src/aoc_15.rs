@@ -0,0 +1,148 @@
+use std::fmt::{self, Display, Formatter};
+
+use color_eyre::eyre::Result;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::alpha1,
+    combinator::{consumed, map, opt},
+    multi::separated_list1,
+    sequence::{pair, preceded, terminated},
+};
+
+use crate::util::{parse_stdin, parse_usize};
+
+const N_BOXES: usize = 256;
+
+fn hash(s: &str) -> usize {
+    s.bytes().fold(0, |h, b| (h + b as usize) * 17 % N_BOXES)
+}
+
+#[derive(Debug)]
+enum Step {
+    Remove { label: String },
+    Insert { label: String, focal_length: usize },
+}
+
+impl Step {
+    fn parse(s: &str) -> nom::IResult<&str, Self> {
+        map(
+            pair(
+                alpha1,
+                alt((
+                    map(tag("-"), |_| None),
+                    map(preceded(tag("="), parse_usize), Some),
+                )),
+            ),
+            |(label, focal_length): (&str, _)| match focal_length {
+                None => Step::Remove {
+                    label: label.to_owned(),
+                },
+                Some(focal_length) => Step::Insert {
+                    label: label.to_owned(),
+                    focal_length,
+                },
+            },
+        )(s)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Lens {
+    label: String,
+    focal_length: usize,
+}
+
+/// Boxes of lenses, each one in insertion order.
+#[derive(Debug)]
+struct Boxes {
+    boxes: Vec<Vec<Lens>>,
+}
+
+impl Boxes {
+    fn new() -> Self {
+        Boxes {
+            boxes: vec![vec![]; N_BOXES],
+        }
+    }
+
+    fn apply(&mut self, step: &Step) {
+        match step {
+            Step::Remove { label } => {
+                self.boxes[hash(label)].retain(|lens| lens.label != *label);
+            }
+            Step::Insert {
+                label,
+                focal_length,
+            } => {
+                let lenses = &mut self.boxes[hash(label)];
+                match lenses.iter_mut().find(|lens| lens.label == *label) {
+                    Some(lens) => lens.focal_length = *focal_length,
+                    None => lenses.push(Lens {
+                        label: label.clone(),
+                        focal_length: *focal_length,
+                    }),
+                }
+            }
+        }
+    }
+
+    fn focusing_power(&self) -> usize {
+        self.boxes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, lenses)| {
+                lenses
+                    .iter()
+                    .enumerate()
+                    .map(move |(j, lens)| (i + 1) * (j + 1) * lens.focal_length)
+            })
+            .sum()
+    }
+}
+
+/// Non-empty boxes, as drawn in the puzzle.
+impl Display for Boxes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, lenses) in self.boxes.iter().enumerate() {
+            if lenses.is_empty() {
+                continue;
+            }
+            write!(f, "Box {i}:")?;
+            for lens in lenses {
+                write!(f, " [{} {}]", lens.label, lens.focal_length)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Usage: `15 [-v]`. With `-v`, print the boxes after every step.
+pub fn aoc_15(args: &[String]) -> Result<(usize, usize)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    // Steps along with their text, which is what part 1 hashes.
+    let steps = parse_stdin(|s| {
+        terminated(
+            separated_list1(
+                tag(","),
+                map(consumed(Step::parse), |(text, step)| {
+                    (text.to_owned(), step)
+                }),
+            ),
+            opt(tag("\n")),
+        )(s)
+    })?;
+
+    let part1 = steps.iter().map(|(text, _)| hash(text)).sum();
+
+    let mut boxes = Boxes::new();
+    for (text, step) in steps.iter() {
+        boxes.apply(step);
+        if verbose {
+            println!("After \"{text}\":\n{boxes}");
+        }
+    }
+    let part2 = boxes.focusing_power();
+    Ok((part1, part2))
+}
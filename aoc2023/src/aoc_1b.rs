@@ -1,49 +1,160 @@
 use std::{
-    cmp::max,
+    collections::{HashMap, VecDeque},
     io::{stdin, BufRead},
 };
 
 use color_eyre::{eyre::eyre, Result};
 
-static DIGITS: &[&str] = &[
+static DIGITS: &[&str] = &["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+static ENGLISH: &[&str] = &[
     "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
 ];
 
-fn find_digit(line: &str, left: bool) -> Result<usize> {
-    let mut found = None;
-    for digit in 1..=9 {
-        for pattern in [&digit.to_string(), DIGITS[digit - 1]] {
-            let idx = if left {
-                line.find(pattern)
-            } else {
-                line.rfind(pattern)
-            };
-            if let Some(idx) = idx {
-                let key = if left {
-                    line.len() - idx
-                } else {
-                    idx + pattern.len()
+static GERMAN: &[&str] = &[
+    "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+];
+
+/// Patterns to look for, with the digit each one stands for.
+fn vocabulary(name: &str) -> Result<Vec<(String, usize)>> {
+    let mut patterns: Vec<(String, usize)> = DIGITS
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.to_string(), i + 1))
+        .collect();
+    let words = match name {
+        "digits" => return Ok(patterns),
+        "en" => ENGLISH,
+        "en-zero" => {
+            patterns.push(("0".to_owned(), 0));
+            patterns.push(("zero".to_owned(), 0));
+            ENGLISH
+        }
+        "de" => GERMAN,
+        _ => return Err(eyre!("unknown vocabulary: {name}")),
+    };
+    patterns.extend(
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.to_string(), i + 1)),
+    );
+    Ok(patterns)
+}
+
+#[derive(Default)]
+struct Node {
+    next: HashMap<u8, usize>,
+    fail: usize,
+    /// Patterns ending at this node, including through failure links.
+    outputs: Vec<usize>,
+}
+
+/// Aho–Corasick automaton: finds all (possibly overlapping) occurrences of
+/// a set of patterns in one pass over the text.
+struct Matcher {
+    nodes: Vec<Node>,
+    lens: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Match {
+    pattern: usize,
+    start: usize,
+}
+
+impl Matcher {
+    fn new<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut nodes = vec![Node::default()];
+        let mut lens = vec![];
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            let mut cur = 0;
+            for b in pattern.bytes() {
+                cur = match nodes[cur].next.get(&b) {
+                    Some(next) => *next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[cur].next.insert(b, next);
+                        next
+                    }
                 };
-                found = max(found, Some((key, digit)));
             }
+            nodes[cur].outputs.push(i);
+            lens.push(pattern.len());
         }
+
+        // Breadth-first, so that failure links always point to nodes that
+        // are already done.
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+        while let Some(cur) = queue.pop_front() {
+            let edges: Vec<(u8, usize)> = nodes[cur].next.iter().map(|(b, n)| (*b, *n)).collect();
+            for (b, next) in edges {
+                let mut fail = nodes[cur].fail;
+                while fail != 0 && !nodes[fail].next.contains_key(&b) {
+                    fail = nodes[fail].fail;
+                }
+                let fail = match nodes[fail].next.get(&b) {
+                    Some(n) if *n != next => *n,
+                    _ => 0,
+                };
+                nodes[next].fail = fail;
+                let inherited = nodes[fail].outputs.clone();
+                nodes[next].outputs.extend(inherited);
+                queue.push_back(next);
+            }
+        }
+        Matcher { nodes, lens }
     }
-    if let Some((_key, digit)) = found {
-        Ok(digit)
-    } else {
-        Err(eyre!("no digits found in {line}"))
+
+    fn find_overlapping(&self, text: &str) -> Vec<Match> {
+        let mut matches = vec![];
+        let mut cur = 0;
+        for (i, b) in text.bytes().enumerate() {
+            while cur != 0 && !self.nodes[cur].next.contains_key(&b) {
+                cur = self.nodes[cur].fail;
+            }
+            cur = self.nodes[cur].next.get(&b).copied().unwrap_or(0);
+            for pattern in self.nodes[cur].outputs.iter() {
+                matches.push(Match {
+                    pattern: *pattern,
+                    start: i + 1 - self.lens[*pattern],
+                });
+            }
+        }
+        matches
     }
 }
 
-pub fn aoc_1b() -> Result<usize> {
+fn calibration_sum(vocabulary: &[(String, usize)], verbose: bool) -> Result<usize> {
+    let matcher = Matcher::new(vocabulary.iter().map(|(s, _)| s.as_str()));
     let stdin = stdin();
     let mut result = 0;
     for line_res in stdin.lock().lines() {
         let line: String = line_res?;
-        let first = find_digit(&line, true)?;
-        let last = find_digit(&line, false)?;
-        eprintln!("{line} -> {first} {last}");
+        let matches = matcher.find_overlapping(&line);
+        // Matches come in order of their end.
+        let first = matches
+            .iter()
+            .min_by_key(|m| m.start)
+            .ok_or(eyre!("no digits found in {line}"))?;
+        let last = matches.last().unwrap();
+        let first = vocabulary[first.pattern].1;
+        let last = vocabulary[last.pattern].1;
+        if verbose {
+            eprintln!("{line} -> {first} {last}");
+        }
         result += first * 10 + last;
     }
     Ok(result)
 }
+
+pub fn aoc_1() -> Result<usize> {
+    calibration_sum(&vocabulary("digits")?, false)
+}
+
+/// Usage: `1b [digits|en|en-zero|de]` (English by default).
+pub fn aoc_1b(args: &[String]) -> Result<usize> {
+    let name = args.first().map_or("en", String::as_str);
+    calibration_sum(&vocabulary(name)?, true)
+}
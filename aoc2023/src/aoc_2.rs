@@ -1,17 +1,24 @@
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 
 use crate::util;
 use nom::{
-    branch::alt,
     bytes::complete::tag,
+    character::complete::alpha1,
     combinator::map,
     multi::{many0, separated_list0, separated_list1},
-    sequence::{delimited, terminated, tuple},
+    sequence::{delimited, separated_pair, terminated, tuple},
 };
 
+/// Number of cubes of each colour.
+type Bag = BTreeMap<String, usize>;
+
+const DEFAULT_BAG: [(&str, usize); 3] = [("red", 12), ("green", 13), ("blue", 14)];
+
 #[derive(Debug)]
 struct Game {
+    id: usize,
     rounds: Vec<Round>,
 }
 
@@ -22,83 +29,93 @@ impl Game {
                 delimited(tag("Game "), util::parse_usize, tag(": ")),
                 separated_list1(tag("; "), Round::parse),
             )),
-            |(_, rounds)| Game { rounds },
+            |(id, rounds)| Game { id, rounds },
         )(s)
     }
 
-    fn possible(&self, red: usize, green: usize, blue: usize) -> bool {
-        for round in &self.rounds {
-            if !round.possible(red, green, blue) {
-                return false;
-            }
-        }
-        true
+    /// Colours for which some round shows more cubes than the bag has.
+    fn violations(&self, bag: &Bag) -> Vec<&str> {
+        self.min_bag()
+            .into_iter()
+            .filter(|(color, n)| *n > bag.get(*color).copied().unwrap_or(0))
+            .map(|(color, _)| color)
+            .collect()
     }
 
-    fn min_cubes(&self) -> (usize, usize, usize) {
-        let mut red = 0;
-        let mut green = 0;
-        let mut blue = 0;
-
+    fn min_bag(&self) -> BTreeMap<&str, usize> {
+        let mut bag = BTreeMap::new();
         for round in &self.rounds {
-            red = std::cmp::max(red, round.red);
-            green = std::cmp::max(green, round.green);
-            blue = std::cmp::max(blue, round.blue);
+            for (color, n) in round.cubes.iter() {
+                let max = bag.entry(color.as_str()).or_insert(0);
+                *max = std::cmp::max(*max, *n);
+            }
         }
-
-        (red, green, blue)
+        bag
     }
 }
 
 #[derive(Debug)]
 struct Round {
-    pub red: usize,
-    pub green: usize,
-    pub blue: usize,
+    cubes: Bag,
 }
 
 impl Round {
     fn parse(s: &str) -> nom::IResult<&str, Self> {
-        let mut round = Round {
-            red: 0,
-            green: 0,
-            blue: 0,
-        };
-        let (rest, tuples) = separated_list0(
+        let (rest, pairs) = separated_list0(
             tag(", "),
-            tuple((
-                util::parse_usize,
-                tag(" "),
-                alt((tag("red"), tag("green"), tag("blue"))),
-            )),
+            separated_pair(util::parse_usize, tag(" "), alpha1),
         )(s)?;
-        for &(n, _, color) in tuples.iter() {
-            match color {
-                "red" => round.red = n,
-                "green" => round.green = n,
-                "blue" => round.blue = n,
-                _ => unreachable!("wrong color: {color}"),
-            }
-        }
-        Ok((rest, round))
+        let cubes = pairs
+            .into_iter()
+            .map(|(n, color)| (color.to_owned(), n))
+            .collect();
+        Ok((rest, Round { cubes }))
     }
+}
 
-    fn possible(&self, red: usize, green: usize, blue: usize) -> bool {
-        self.red <= red && self.green <= green && self.blue <= blue
+fn parse_bag(args: &[String]) -> Result<Bag> {
+    if args.is_empty() {
+        return Ok(DEFAULT_BAG
+            .iter()
+            .map(|(color, n)| (color.to_string(), *n))
+            .collect());
     }
+    args.iter()
+        .map(|arg| {
+            let (color, n) = arg
+                .split_once('=')
+                .ok_or(eyre!("expected COLOR=N, got: {arg}"))?;
+            Ok((color.to_owned(), n.parse()?))
+        })
+        .collect()
 }
 
-pub fn aoc_2() -> Result<(usize, usize)> {
+/// Usage: `2 [-v] [COLOR=N...]` (by default, 12 red, 13 green and 14 blue).
+/// Part 2 multiplies the minimal counts of the bag's colours. With `-v`, print
+/// the minimal bag and the violated colours of every game.
+pub fn aoc_2(args: &[String]) -> Result<(usize, usize)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    let bag = parse_bag(if verbose { &args[1..] } else { args })?;
     let games = util::parse_stdin(|s| many0(terminated(Game::parse, tag("\n")))(s))?;
 
     let mut part1 = 0;
     let mut part2 = 0;
-    for (i, game) in games.iter().enumerate() {
-        if game.possible(12, 13, 14) {
-            part1 += i + 1
+    for game in games.iter() {
+        let violations = game.violations(&bag);
+        if violations.is_empty() {
+            part1 += game.id;
+        }
+        let min_bag = game.min_bag();
+        if verbose {
+            println!(
+                "game {}: minimal bag {:?}, violates {:?}",
+                game.id, min_bag, violations
+            );
         }
-        let (red, green, blue) = game.min_cubes();
-        part2 += red * green * blue;
+        part2 += bag
+            .keys()
+            .map(|color| min_bag.get(color.as_str()).copied().unwrap_or(0))
+            .product::<usize>();
     }
 
     Ok((part1, part2))
@@ -1,21 +1,39 @@
 use crate::util;
 use color_eyre::eyre::Result;
+use std::collections::BTreeMap;
 use std::io::{stdin, Read};
 
 use nom::{
     bytes::complete::tag,
     character::complete::{space1, u8},
-    combinator::{all_consuming, map},
-    multi::separated_list1,
-    sequence::tuple,
+    combinator::{all_consuming, map, verify},
+    multi::fold_many1,
+    sequence::{preceded, tuple},
 };
 
-type Number = u8;
+/// Set of card numbers, which are all below 128.
+#[derive(Debug, Clone, Copy, Default)]
+struct NumberSet(u128);
+
+impl NumberSet {
+    fn insert(self, n: u8) -> Self {
+        NumberSet(self.0 | 1 << n)
+    }
+
+    fn intersection(self, other: Self) -> Self {
+        NumberSet(self.0 & other.0)
+    }
+
+    fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+}
 
 #[derive(Debug)]
 struct Card {
-    winning: Vec<Number>,
-    actual: Vec<Number>,
+    id: usize,
+    winning: NumberSet,
+    actual: NumberSet,
 }
 
 impl Card {
@@ -26,62 +44,111 @@ impl Card {
                 space1,
                 util::parse_usize,
                 tag(":"),
-                space1,
-                parse_number_list,
+                parse_number_set,
                 tag(" |"),
-                space1,
-                parse_number_list,
+                parse_number_set,
             )),
-            |(_, _, _, _, _, winning, _, _, actual)| Card { winning, actual },
+            |(_, _, id, _, winning, _, actual)| Card {
+                id,
+                winning,
+                actual,
+            },
         )(s)
     }
 
-    fn score(self: &Self) -> usize {
-        let mut score = 0;
-        for number in &self.actual {
-            if self.winning.contains(number) {
-                score += 1;
-            }
-        }
-        score
+    fn matches(&self) -> usize {
+        self.winning.intersection(self.actual).len()
     }
 }
 
-fn parse_number(s: &str) -> nom::IResult<&str, Number> {
-    u8(s)
+fn parse_number_set(s: &str) -> nom::IResult<&str, NumberSet> {
+    fold_many1(
+        preceded(space1, verify(u8, |n| *n < 128)),
+        NumberSet::default,
+        NumberSet::insert,
+    )(s)
 }
 
-fn parse_number_list(s: &str) -> nom::IResult<&str, Vec<Number>> {
-    separated_list1(space1, parse_number)(s)
+/// Outcome of playing all cards by the part 2 rules.
+struct Cascade {
+    /// Final number of copies of each card, including the original.
+    copies: Vec<usize>,
+    /// Number of cards a single copy of each card wins, directly or through
+    /// the copies it wins.
+    won: Vec<usize>,
 }
 
-pub fn aoc_4() -> Result<(usize, usize)> {
+impl Cascade {
+    fn run(cards: &[Card]) -> Self {
+        let matches: Vec<usize> = cards.iter().map(Card::matches).collect();
+        let n = cards.len();
+
+        let mut copies = vec![1; n];
+        for i in 0..n {
+            for j in i + 1..(i + 1 + matches[i]).min(n) {
+                copies[j] += copies[i];
+            }
+        }
+
+        // Later cards never win earlier ones, so go backwards.
+        let mut won = vec![0; n];
+        for i in (0..n).rev() {
+            won[i] = (i + 1..(i + 1 + matches[i]).min(n))
+                .map(|j| 1 + won[j])
+                .sum();
+        }
+
+        Cascade { copies, won }
+    }
+
+    /// Number of cards for each final copy count.
+    fn distribution(&self) -> BTreeMap<usize, usize> {
+        let mut distribution = BTreeMap::new();
+        for count in self.copies.iter() {
+            *distribution.entry(*count).or_insert(0) += 1;
+        }
+        distribution
+    }
+}
+
+/// Usage: `4 [-v]`. With `-v`, print the copies and cards won for each card,
+/// and how many cards end up with each number of copies.
+pub fn aoc_4(args: &[String]) -> Result<(usize, usize)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
     let mut s: String = "".to_owned();
     stdin().read_to_string(&mut s)?;
 
     let cards: Vec<Card> = s
         .lines()
         .map(|line| {
-            let (_rest, card) =
-                all_consuming(Card::parse)(line).or_else(|err| Err(err.to_owned()))?;
+            let (_rest, card) = all_consuming(Card::parse)(line).map_err(|err| err.to_owned())?;
             Ok(card)
         })
         .collect::<Result<Vec<Card>>>()?;
 
-    let mut part1 = 0;
-    let mut counts: Vec<usize> = cards.iter().map(|_| 1).collect();
-    for (i, card) in cards.iter().enumerate() {
-        let score = card.score();
-        if score > 0 {
-            part1 += 1 << (score - 1);
-            let count = counts[i];
-            for j in i + 1..=i + score {
-                if j < counts.len() {
-                    counts[j] += count;
-                }
-            }
+    let part1 = cards
+        .iter()
+        .map(|card| match card.matches() {
+            0 => 0,
+            m => 1 << (m - 1),
+        })
+        .sum();
+
+    let cascade = Cascade::run(&cards);
+    if verbose {
+        for (i, card) in cards.iter().enumerate() {
+            println!(
+                "card {}: {} matches, {} copies, each wins {} cards",
+                card.id,
+                card.matches(),
+                cascade.copies[i],
+                cascade.won[i]
+            );
+        }
+        for (count, n) in cascade.distribution() {
+            println!("{n} cards with {count} copies");
         }
     }
-    let part2 = counts.iter().sum();
+    let part2 = cascade.copies.iter().sum();
     Ok((part1, part2))
 }
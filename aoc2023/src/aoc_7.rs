@@ -0,0 +1,151 @@
+use color_eyre::eyre::{eyre, Result};
+use nom::{
+    bytes::complete::{tag, take_while_m_n},
+    combinator::map,
+    multi::many1,
+    sequence::{separated_pair, terminated},
+};
+
+use crate::util::{parse_stdin, parse_usize};
+
+const CARDS: &str = "23456789TJQKA";
+const JOKER: char = 'J';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Category {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
+
+impl Category {
+    /// Category of a hand given the sizes of its groups of equal cards, in
+    /// decreasing order.
+    fn from_groups(groups: &[usize]) -> Self {
+        match groups {
+            [5, ..] => Category::FiveOfAKind,
+            [4, ..] => Category::FourOfAKind,
+            [3, 2, ..] => Category::FullHouse,
+            [3, ..] => Category::ThreeOfAKind,
+            [2, 2, ..] => Category::TwoPair,
+            [2, ..] => Category::OnePair,
+            _ => Category::HighCard,
+        }
+    }
+}
+
+/// How hands are ranked: a wildcard is the weakest card on its own, but
+/// counts as whatever card makes the best category.
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    wildcard: Option<char>,
+}
+
+impl Rules {
+    fn strength(&self, card: char) -> Result<usize> {
+        let i = CARDS.find(card).ok_or(eyre!("unknown card: {card}"))?;
+        Ok(match self.wildcard {
+            Some(wildcard) if card == wildcard => 0,
+            Some(wildcard) if CARDS.find(wildcard) > Some(i) => i + 1,
+            _ => i,
+        })
+    }
+}
+
+/// A hand as ranked under some rules. Fields are in comparison order, so
+/// hands made with the same rules compare like the puzzle says.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Hand {
+    category: Category,
+    strengths: [usize; 5],
+    cards: String,
+}
+
+impl Hand {
+    fn new(cards: &str, rules: &Rules) -> Result<Self> {
+        let chars: Vec<char> = cards.chars().collect();
+        let mut strengths = [0; 5];
+        if chars.len() != strengths.len() {
+            return Err(eyre!("expected 5 cards: {cards}"));
+        }
+        for (strength, card) in strengths.iter_mut().zip(chars.iter()) {
+            *strength = rules.strength(*card)?;
+        }
+
+        let mut wildcards = 0;
+        let mut counts = [0; CARDS.len()];
+        for card in chars.iter() {
+            if Some(*card) == rules.wildcard {
+                wildcards += 1;
+            } else {
+                counts[CARDS.find(*card).unwrap()] += 1;
+            }
+        }
+        let mut groups: Vec<usize> = counts.into_iter().filter(|n| *n > 0).collect();
+        groups.sort_by(|a, b| b.cmp(a));
+        // Wildcards are always best used to grow the largest group.
+        match groups.first_mut() {
+            Some(largest) => *largest += wildcards,
+            None => groups.push(wildcards),
+        }
+
+        Ok(Hand {
+            category: Category::from_groups(&groups),
+            strengths,
+            cards: cards.to_owned(),
+        })
+    }
+}
+
+fn parse_line(s: &str) -> nom::IResult<&str, (String, usize)> {
+    separated_pair(
+        map(
+            take_while_m_n(5, 5, |c: char| c.is_ascii_alphanumeric()),
+            str::to_owned,
+        ),
+        tag(" "),
+        parse_usize,
+    )(s)
+}
+
+fn winnings(lines: &[(String, usize)], rules: &Rules, verbose: bool) -> Result<usize> {
+    let mut hands = lines
+        .iter()
+        .map(|(cards, bid)| Ok((Hand::new(cards, rules)?, *bid)))
+        .collect::<Result<Vec<_>>>()?;
+    hands.sort();
+    let mut result = 0;
+    for (i, (hand, bid)) in hands.iter().enumerate() {
+        if verbose {
+            println!("{} {}: {:?}", i + 1, hand.cards, hand.category);
+        }
+        result += (i + 1) * bid;
+    }
+    Ok(result)
+}
+
+/// Usage: `7 [-v] [WILDCARD]`. Part 2 uses the given wildcard instead of the
+/// joker. With `-v`, print the ranking of the hands for both parts.
+pub fn aoc_7(args: &[String]) -> Result<(usize, usize)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    let wildcard = match &args[verbose as usize..] {
+        [] => JOKER,
+        [card] if card.len() == 1 && CARDS.contains(card.as_str()) => card.chars().next().unwrap(),
+        _ => return Err(eyre!("expected a single card as wildcard")),
+    };
+
+    let lines = parse_stdin(|s| many1(terminated(parse_line, tag("\n")))(s))?;
+    let part1 = winnings(&lines, &Rules { wildcard: None }, verbose)?;
+    let part2 = winnings(
+        &lines,
+        &Rules {
+            wildcard: Some(wildcard),
+        },
+        verbose,
+    )?;
+    Ok((part1, part2))
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{eyre, Result};
+use itertools::Itertools;
+use nom::{
+    bytes::complete::{tag, take_while1},
+    character::complete::one_of,
+    combinator::map,
+    multi::many1,
+    sequence::{delimited, separated_pair, terminated, tuple},
+};
+
+use crate::util::parse_stdin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(Debug)]
+struct Network {
+    turns: Vec<Turn>,
+    names: Vec<String>,
+    /// Left and right neighbours of each node.
+    next: Vec<(usize, usize)>,
+}
+
+type Line = (String, (String, String));
+
+fn parse_name(s: &str) -> nom::IResult<&str, String> {
+    map(
+        take_while1(|c: char| c.is_ascii_alphanumeric()),
+        str::to_owned,
+    )(s)
+}
+
+fn parse_line(s: &str) -> nom::IResult<&str, Line> {
+    separated_pair(
+        parse_name,
+        tag(" = "),
+        delimited(
+            tag("("),
+            separated_pair(parse_name, tag(", "), parse_name),
+            tag(")"),
+        ),
+    )(s)
+}
+
+fn parse_input(s: &str) -> nom::IResult<&str, (Vec<Turn>, Vec<Line>)> {
+    tuple((
+        terminated(
+            many1(map(one_of("LR"), |c| match c {
+                'L' => Turn::Left,
+                _ => Turn::Right,
+            })),
+            tag("\n\n"),
+        ),
+        many1(terminated(parse_line, tag("\n"))),
+    ))(s)
+}
+
+impl Network {
+    fn new(turns: Vec<Turn>, lines: Vec<Line>) -> Result<Self> {
+        let ids: HashMap<&str, usize> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect();
+        let id = |name: &str| ids.get(name).copied().ok_or(eyre!("unknown node: {name}"));
+        let next = lines
+            .iter()
+            .map(|(_, (left, right))| Ok((id(left)?, id(right)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let names = lines.into_iter().map(|(name, _)| name).collect();
+        Ok(Network { turns, names, next })
+    }
+
+    fn id(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    fn step(&self, node: usize, t: usize) -> usize {
+        match self.turns[t % self.turns.len()] {
+            Turn::Left => self.next[node].0,
+            Turn::Right => self.next[node].1,
+        }
+    }
+
+    /// Follow the instructions from `start` until the state (node, position
+    /// in the instructions) repeats.
+    fn walk(&self, start: usize, is_end: impl Fn(&str) -> bool) -> Walk {
+        let n_turns = self.turns.len();
+        let mut seen = vec![None; self.names.len() * n_turns];
+        let mut hits = vec![];
+        let mut node = start;
+        let mut t = 0;
+        loop {
+            let state = node * n_turns + t % n_turns;
+            if let Some(prev) = seen[state] {
+                return Walk {
+                    pre_period: prev,
+                    period: t - prev,
+                    hits,
+                };
+            }
+            seen[state] = Some(t);
+            if is_end(&self.names[node]) {
+                hits.push(t);
+            }
+            node = self.step(node, t);
+            t += 1;
+        }
+    }
+}
+
+/// Where a walk ends up: after `pre_period` steps, it repeats every `period`
+/// steps. `hits` lists the steps before `pre_period + period` spent on an end
+/// node.
+#[derive(Debug)]
+struct Walk {
+    pre_period: usize,
+    period: usize,
+    hits: Vec<usize>,
+}
+
+impl Walk {
+    fn hits_at(&self, t: usize) -> bool {
+        let t = if t < self.pre_period {
+            t
+        } else {
+            self.pre_period + (t - self.pre_period) % self.period
+        };
+        self.hits.binary_search(&t).is_ok()
+    }
+
+    /// Offsets in the cycle of the hits, modulo the period.
+    fn cycle_hits(&self) -> Vec<usize> {
+        self.hits
+            .iter()
+            .filter(|t| **t >= self.pre_period)
+            .map(|t| t % self.period)
+            .collect()
+    }
+}
+
+/// Returns `(g, x)` such that `g = gcd(a, b)` and `a * x = g (mod b)`.
+fn ext_gcd(a: i128, b: i128) -> (i128, i128) {
+    let (mut r0, mut r1) = (a, b);
+    let (mut x0, mut x1) = (1, 0);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (x0, x1) = (x1, x0 - q * x1);
+    }
+    (r0, x0)
+}
+
+/// Combine `t = r1 (mod m1)` and `t = r2 (mod m2)`, for moduli that need not
+/// be coprime.
+fn crt((r1, m1): (i128, i128), (r2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, x) = ext_gcd(m1, m2);
+    if (r2 - r1) % g != 0 {
+        return None;
+    }
+    let m = m1 / g * m2;
+    let k = (r2 - r1) / g * x % (m2 / g);
+    Some(((r1 + m1 * k).rem_euclid(m), m))
+}
+
+/// First step (after the start) at which all walks are on an end node.
+fn first_common_hit(walks: &[Walk]) -> Option<usize> {
+    // Before every walk is in its cycle, just check each step.
+    let start = walks.iter().map(|w| w.pre_period).max()?.max(1);
+    if let Some(t) = (1..start).find(|t| walks.iter().all(|w| w.hits_at(*t))) {
+        return Some(t);
+    }
+
+    let mut systems = vec![(0, 1)];
+    for walk in walks {
+        let period = walk.period as i128;
+        systems = systems
+            .iter()
+            .cartesian_product(walk.cycle_hits())
+            .filter_map(|(system, r)| crt(*system, (r as i128, period)))
+            .unique()
+            .collect();
+    }
+    systems
+        .into_iter()
+        .map(|(r, m)| {
+            let start = start as i128;
+            start + (r - start).rem_euclid(m)
+        })
+        .min()
+        .map(|t| t as usize)
+}
+
+/// Usage: `8 [-v]`. With `-v`, print the pre-period, period and end node
+/// steps of every walk. Part 1 is `None` if there is no `AAA` node.
+pub fn aoc_8(args: &[String]) -> Result<(Option<usize>, Option<usize>)> {
+    let verbose = args.first().map(String::as_str) == Some("-v");
+    let (turns, lines) = parse_stdin(parse_input)?;
+    let network = Network::new(turns, lines)?;
+
+    let part1 = network
+        .id("AAA")
+        .map(|start| network.walk(start, |name| name == "ZZZ"))
+        .and_then(|walk| first_common_hit(&[walk]));
+
+    let walks: Vec<Walk> = (0..network.names.len())
+        .filter(|i| network.names[*i].ends_with('A'))
+        .map(|start| network.walk(start, |name| name.ends_with('Z')))
+        .collect();
+    if verbose {
+        for walk in walks.iter() {
+            println!("{:?}", walk);
+        }
+    }
+    let part2 = first_common_hit(&walks);
+
+    Ok((part1, part2))
+}
@@ -2,30 +2,43 @@ mod util;
 
 mod aoc_10;
 mod aoc_11;
+mod aoc_12;
+mod aoc_13;
+mod aoc_14;
+mod aoc_15;
 mod aoc_1b;
 mod aoc_2;
 mod aoc_4;
 mod aoc_5;
 mod aoc_6;
+mod aoc_7;
+mod aoc_8;
 mod aoc_9;
 
 fn main() {
     color_eyre::install().unwrap();
 
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("usage: {} <num>", &args[0]);
+    if args.len() < 2 {
+        println!("usage: {} <num> [args...]", &args[0]);
         std::process::exit(1);
     }
     match args[1].as_str() {
-        "1b" => println!("{:?}", aoc_1b::aoc_1b().unwrap()),
-        "2" => println!("{:?}", aoc_2::aoc_2().unwrap()),
-        "4" => println!("{:?}", aoc_4::aoc_4().unwrap()),
+        "1" => println!("{:?}", aoc_1b::aoc_1().unwrap()),
+        "1b" => println!("{:?}", aoc_1b::aoc_1b(&args[2..]).unwrap()),
+        "2" => println!("{:?}", aoc_2::aoc_2(&args[2..]).unwrap()),
+        "4" => println!("{:?}", aoc_4::aoc_4(&args[2..]).unwrap()),
         "5" => println!("{:?}", aoc_5::aoc_5().unwrap()),
         "6" => println!("{:?}", aoc_6::aoc_6().unwrap()),
+        "7" => println!("{:?}", aoc_7::aoc_7(&args[2..]).unwrap()),
+        "8" => println!("{:?}", aoc_8::aoc_8(&args[2..]).unwrap()),
         "9" => println!("{:?}", aoc_9::aoc_9().unwrap()),
         "10" => println!("{:?}", aoc_10::aoc_10().unwrap()),
-        "11" => println!("{:?}", aoc_11::aoc_11().unwrap()),
+        "11" => println!("{:?}", aoc_11::aoc_11(&args[2..]).unwrap()),
+        "12" => println!("{:?}", aoc_12::aoc_12(&args[2..]).unwrap()),
+        "13" => println!("{:?}", aoc_13::aoc_13(&args[2..]).unwrap()),
+        "14" => println!("{:?}", aoc_14::aoc_14(&args[2..]).unwrap()),
+        "15" => println!("{:?}", aoc_15::aoc_15(&args[2..]).unwrap()),
         _ => {
             println!("unrecognized num: {}", &args[1]);
             std::process::exit(1);